@@ -0,0 +1,25 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use mel0n::daily::{DailyDate, Date};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Sets the daily challenge to today, going by UTC so everyone's day changes at the same time.
+pub struct DailyDatePlugin;
+
+impl Plugin for DailyDatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, set_today);
+    }
+}
+
+fn set_today(mut date: ResMut<DailyDate>) {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => {
+            let days = since_epoch.as_secs() / SECS_PER_DAY;
+            date.0 = Date::from_unix_days(u32::try_from(days).unwrap_or(u32::MAX));
+        }
+        Err(err) => warn!("System clock is before 1970, keeping the default daily date: {err}"),
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use mel0n::fruit::{FruitVisuals, FruitVisualsPlugin, TIER_DIAMETERS, Tier};
+
+/// A fruit's shape, sized by the fruit's scale.
+pub fn fruit_mesh() -> Mesh {
+    Circle::default().into()
+}
+
+/// Draws each fruit as a circle, coloured by tier from red round the colour wheel.
+#[derive(Resource)]
+pub struct FruitMeshes {
+    mesh: Handle<Mesh>,
+    materials: Vec<Handle<ColorMaterial>>,
+}
+
+impl FromWorld for FruitMeshes {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(fruit_mesh());
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        let tiers = TIER_DIAMETERS.len();
+        let materials = (0..tiers)
+            .map(|tier| {
+                let hue = 300. * tier as f32 / (tiers - 1) as f32;
+                materials.add(Color::hsl(hue, 0.8, 0.6))
+            })
+            .collect();
+        Self { mesh, materials }
+    }
+}
+
+impl FruitVisuals for FruitMeshes {
+    fn apply(&self, tier: Tier, fruit: &mut EntityCommands) {
+        let material = &self.materials[usize::from(tier.0.min(Tier::MAX.0))];
+        fruit.insert((Mesh2d(self.mesh.clone()), MeshMaterial2d(material.clone())));
+    }
+}
+
+pub struct FruitMeshesPlugin;
+
+impl Plugin for FruitMeshesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FruitMeshes>()
+            .add_plugins(FruitVisualsPlugin::<FruitMeshes>::default());
+    }
+}
@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use mel0n::{
+    Mel0nSetupSet,
+    fruit::Fruit,
+    ghost::{Ghost, GhostRoot, GhostRun},
+    mode::GameState,
+};
+
+use crate::fruit::fruit_mesh;
+
+/// How see-through the ghost's fruit and dropper are.
+const GHOST_ALPHA: f32 = 0.3;
+
+/// Draws the ghost's fruit and dropper, faded out.
+pub struct GhostVisualsPlugin;
+
+impl Plugin for GhostVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_ghost_dropper.after(Mel0nSetupSet))
+            .add_systems(Update, (add_ghost_visuals, update_ghost_dropper));
+    }
+}
+
+#[derive(Component)]
+struct GhostDropper;
+
+#[derive(Resource)]
+struct GhostMaterial(Handle<ColorMaterial>);
+
+fn spawn_ghost_dropper(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    root: Single<Entity, With<GhostRoot>>,
+) {
+    let material = materials.add(Color::linear_rgba(1.0, 1.0, 1.0, GHOST_ALPHA));
+    let entity = commands
+        .spawn((
+            GhostDropper,
+            Mesh2d(meshes.add(fruit_mesh())),
+            MeshMaterial2d(material.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+        ))
+        .id();
+    commands.entity(*root).add_child(entity);
+    commands.insert_resource(GhostMaterial(material));
+}
+
+fn add_ghost_visuals(
+    mut commands: Commands,
+    fruits: Query<Entity, (Added<Ghost>, With<Fruit>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    material: Res<GhostMaterial>,
+) {
+    for entity in &fruits {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(fruit_mesh())),
+            MeshMaterial2d(material.0.clone()),
+        ));
+    }
+}
+
+fn update_ghost_dropper(
+    preview: Single<(&mut Transform, &mut Visibility), With<GhostDropper>>,
+    ghost: Option<Res<GhostRun>>,
+    state: Res<State<GameState>>,
+) {
+    let (mut transform, mut visibility) = preview.into_inner();
+    let Some(ghost) = ghost.filter(|ghost| ghost.dropper.can_drop()) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *transform = Transform::from_translation(ghost.dropper.spawn_pos().extend(2.0))
+        .with_scale(Vec2::splat(ghost.dropper.current.diameter()).extend(1.));
+    *visibility = if *state == GameState::Playing {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
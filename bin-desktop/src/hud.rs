@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use mel0n::{
+    Mel0nSetupSet, Root,
+    bindings::{ButtonBindings, ControlsMenu, KeyBindings},
+    countdown::Countdown,
+    daily::{DailyBests, DailyDate},
+    dropper::Dropper,
+    ghost::GhostRun,
+    input::Action,
+    level::{ActiveLevel, Goal},
+    mode::{GameMode, GameState},
+    overflow::DANGER_LINE,
+    replay::Playback,
+    rules::{GameRules, Overflow},
+    score::{HighScores, Score},
+    wall::{LEFT_WALL, RIGHT_WALL},
+};
+
+use crate::fruit::fruit_mesh;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (setup_hud, spawn_dropper.after(Mel0nSetupSet)))
+            .add_systems(
+                Update,
+                (update_hud, update_menu, update_dropper, update_danger_line),
+            );
+    }
+}
+
+#[derive(Component)]
+struct HudText;
+
+/// Title screen and game over text, in the middle of the screen.
+#[derive(Component)]
+struct MenuText;
+
+#[derive(Component)]
+struct DangerLine;
+
+/// The fruit hanging at the top of the arena, only shown once it can be dropped.
+#[derive(Component)]
+struct DropperPreview;
+
+fn setup_hud(mut commands: Commands) {
+    commands.spawn((
+        HudText,
+        Text::default(),
+        TextFont {
+            font_size: 16.,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.),
+            right: Val::Px(12.),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        children![(
+            MenuText,
+            Text::default(),
+            TextFont {
+                font_size: 20.,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+        )],
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_hud(
+    mut text: Single<&mut Text, With<HudText>>,
+    score: Res<Score>,
+    dropper: Res<Dropper>,
+    rules: Res<GameRules>,
+    mode: Res<GameMode>,
+    high_scores: Res<HighScores>,
+    countdown: Res<Countdown>,
+    time: Res<Time<Virtual>>,
+    level: Option<Res<ActiveLevel>>,
+    daily: Res<DailyDate>,
+    daily_bests: Res<DailyBests>,
+    playback: Option<Res<Playback>>,
+    ghost: Option<Res<GhostRun>>,
+    mut was_paused: Local<bool>,
+) {
+    // Virtual time changes every frame, so only pausing and unpausing it counts.
+    let paused = time.is_paused();
+    let pause_changed = std::mem::replace(&mut *was_paused, paused) != paused;
+    if !(score.is_changed()
+        || dropper.is_changed()
+        || rules.is_changed()
+        || mode.is_changed()
+        || high_scores.is_changed()
+        || countdown.is_changed()
+        || pause_changed
+        || level.as_ref().is_some_and(Res::is_changed)
+        || daily.is_changed()
+        || daily_bests.is_changed()
+        || playback.as_ref().is_some_and(Res::is_added)
+        || ghost.as_ref().is_some_and(Res::is_changed))
+    {
+        return;
+    }
+    let mut hud = format!(
+        "{}\nBest  {}\nScore {}\nNow   {}\nNext  {}",
+        mode.name(),
+        high_scores.best(*mode),
+        score.0,
+        dropper.current.name(),
+        dropper.next.name()
+    );
+    if let Some(ghost) = ghost {
+        hud += &format!("\nGhost {}", ghost.score.0);
+    }
+    if rules.hold {
+        let held = dropper.held.map_or("-", |tier| tier.name());
+        hud += &format!("\nHold  {held}");
+    }
+    if *mode == GameMode::Daily {
+        let date = daily.0;
+        hud += &format!(
+            "\n\n{}-{:02}-{:02}\nToday {}",
+            date.year,
+            date.month,
+            date.day,
+            daily_bests.best(date)
+        );
+    }
+    if let Some(level) = level {
+        let goal = match level.level.goal {
+            Goal::MakeTier(tier) => format!("Make a {}", tier.name()),
+            Goal::Score(score) => format!("Score {score}"),
+        };
+        let drops = dropper.drops_left.unwrap_or_default();
+        hud += &format!("\n\n{}\n{goal}\nDrops {drops}", level.level.name);
+    }
+    if let Some(remaining) = countdown.0 {
+        // Round up so the clock only reads 0:00 once time is actually up.
+        let secs = remaining.as_millis().div_ceil(1000);
+        hud += &format!("\nTime  {}:{:02}", secs / 60, secs % 60);
+    }
+    if playback.is_some() {
+        hud += "\n\nREPLAY";
+    }
+    if paused {
+        hud += "\n\nPAUSED";
+    }
+    text.0 = hud;
+}
+
+/// `KeyC` as just `C`, and so on.
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    match name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+    {
+        Some(short) => short.to_owned(),
+        None => name,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_menu(
+    mut text: Single<&mut Text, With<MenuText>>,
+    state: Res<State<GameState>>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    menu: Res<ControlsMenu>,
+    keys: Res<KeyBindings>,
+    buttons: Res<ButtonBindings>,
+) {
+    if !(state.is_changed()
+        || mode.is_changed()
+        || menu.is_changed()
+        || keys.is_changed()
+        || buttons.is_changed())
+    {
+        return;
+    }
+    let confirm = key_name(keys.confirm);
+    let back = key_name(keys.back);
+    text.0 = match state.get() {
+        GameState::Title => {
+            let mut menu = String::from("mel0n\n\n");
+            for option in GameMode::ALL {
+                let cursor = if option == *mode { ">" } else { " " };
+                menu += &format!("{cursor} {}\n", option.name());
+            }
+            menu + &format!("\n{confirm} to start\n{back} for controls")
+        }
+        GameState::Playing => String::new(),
+        GameState::GameOver => format!(
+            "Game over\n\nScore {}\nBest  {}\n\n{confirm} to continue",
+            score.0,
+            high_scores.best(*mode)
+        ),
+        GameState::LevelComplete => {
+            format!("Level complete!\n\n{confirm} for the next one\n{back} for the title")
+        }
+        GameState::LevelFailed => {
+            format!("Level failed\n\n{confirm} to retry\n{back} for the title")
+        }
+        // Only the GBA asks for the date; the desktop uses today's.
+        GameState::PickDate => String::new(),
+        GameState::Controls => {
+            let mut list = String::from("Controls\n\n");
+            for action in Action::ALL {
+                let cursor = if action == menu.selected { ">" } else { " " };
+                let key = if action == menu.selected && menu.capturing {
+                    String::from("...")
+                } else {
+                    key_name(keys.key(action))
+                };
+                list += &format!(
+                    "{cursor} {:<9} {key:<10} {:?}\n",
+                    action.name(),
+                    buttons.button(action)
+                );
+            }
+            list += &match (menu.capturing, menu.clash) {
+                (true, _) => format!("\nPress a key or button for {}", menu.selected.name()),
+                (false, Some(clash)) => format!("\nAlready used for {}", clash.name()),
+                (false, None) => format!("\n{confirm} to change\n{back} for the title"),
+            };
+            list
+        }
+    };
+}
+
+fn spawn_dropper(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    root: Single<Entity, With<Root>>,
+) {
+    let entity = commands
+        .spawn((
+            DropperPreview,
+            Mesh2d(meshes.add(fruit_mesh())),
+            MeshMaterial2d(materials.add(Color::linear_rgba(1.0, 0.0, 1.0, 0.5))),
+            Transform::default(),
+        ))
+        .id();
+    commands.entity(*root).add_child(entity);
+
+    let width = RIGHT_WALL - LEFT_WALL;
+    let entity = commands
+        .spawn((
+            DangerLine,
+            Sprite::from_color(Color::linear_rgba(1.0, 0.2, 0.2, 0.5), vec2(width, 1.)),
+            Transform::from_xyz(LEFT_WALL + width / 2., DANGER_LINE, 0.5),
+        ))
+        .id();
+    commands.entity(*root).add_child(entity);
+}
+
+fn update_dropper(
+    preview: Single<(&mut Transform, &mut Visibility), With<DropperPreview>>,
+    dropper: Res<Dropper>,
+    state: Res<State<GameState>>,
+) {
+    let (mut transform, mut visibility) = preview.into_inner();
+    *transform = Transform::from_translation(dropper.spawn_pos().extend(2.0))
+        .with_scale(Vec2::splat(dropper.current.diameter()).extend(1.));
+    *visibility = if dropper.can_drop() && *state == GameState::Playing {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
+
+fn update_danger_line(
+    mut visibility: Single<&mut Visibility, With<DangerLine>>,
+    rules: Res<GameRules>,
+) {
+    **visibility = if rules.overflow == Overflow::GameOver {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
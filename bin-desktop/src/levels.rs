@@ -0,0 +1,58 @@
+use std::{fs, path::Path};
+
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use mel0n::level::{Level, Levels};
+
+/// Puzzles live in `assets/levels` as one RON file each, played in file name order.
+pub struct LevelFilesPlugin;
+
+impl Plugin for LevelFilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_levels);
+    }
+}
+
+fn load_levels(mut levels: ResMut<Levels>) {
+    let dir = FileAssetReader::get_base_path().join("assets/levels");
+    match read_levels(&dir) {
+        Ok(loaded) if !loaded.is_empty() => {
+            info!("Loaded {} levels from {}", loaded.len(), dir.display());
+            levels.0 = loaded.into();
+        }
+        Ok(_) => warn!("No levels in {}, using the built-in ones", dir.display()),
+        Err(err) => warn!("Couldn't load levels from {}: {err}", dir.display()),
+    }
+}
+
+fn read_levels(dir: &Path) -> Result<Vec<Level>, Box<dyn std::error::Error>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "ron"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let level = ron::from_str(&fs::read_to_string(path)?)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            Ok(level)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use mel0n::level::LEVELS;
+
+    use super::read_levels;
+
+    #[test]
+    fn level_files_match_builtins() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/levels");
+        let levels = read_levels(&dir).unwrap();
+        assert_eq!(levels, LEVELS);
+    }
+}
@@ -15,14 +15,30 @@ use bevy::{
     prelude::*,
 };
 use mel0n::{
-    Collider, Mel0nBasePlugin, Mel0nPhysicsSet, Mel0nSetupSet, Root, Velocity,
-    fruit::{Collided, FRUIT_DIAMETER, Fruit},
+    ArenaCoords, ArenaSpace, Collider, Mel0nBasePlugin, Mel0nPhysicsSet, Mel0nSetupSet, Root,
+    Velocity,
+    fruit::{Collided, Diameter, Fruit},
+    ghost::{GhostPlugin, GhostRoot},
     physics::ImpulseGizmoEvent,
     wall::{Wall, WallLocation},
 };
 use ops::atan2;
 
-use crate::gamepad_vis::GamepadVisPlugin;
+use crate::{
+    daily::DailyDatePlugin, fruit::FruitMeshesPlugin, gamepad_vis::GamepadVisPlugin,
+    ghost::GhostVisualsPlugin, hud::HudPlugin, levels::LevelFilesPlugin, pointer::PointerPlugin,
+    replays::ReplayFilesPlugin, save::SaveFilesPlugin, settings::SettingsFilePlugin,
+};
+
+mod daily;
+mod fruit;
+mod ghost;
+mod hud;
+mod levels;
+mod pointer;
+mod replays;
+mod save;
+mod settings;
 
 #[derive(Default, Reflect, GizmoConfigGroup)]
 struct MyRoundGizmos {}
@@ -39,6 +55,18 @@ fn main() {
                 ..default()
             }),
             Mel0nBasePlugin,
+            FruitMeshesPlugin,
+            HudPlugin,
+            LevelFilesPlugin,
+            DailyDatePlugin,
+            ReplayFilesPlugin,
+            SaveFilesPlugin,
+            SettingsFilePlugin,
+            PointerPlugin,
+            GhostPlugin {
+                offset: vec3(240., 0., 0.),
+            },
+            GhostVisualsPlugin,
             MeshPickingPlugin,
             DebugPickingPlugin, // GamepadVisPlugin,
         ))
@@ -56,12 +84,15 @@ fn main() {
         // )
         .add_systems(Update, stepping_handler)
         .init_gizmo_group::<MyRoundGizmos>()
+        // Centred on the camera, and flipped, as the world's y goes up.
+        .insert_resource(ArenaSpace(
+            Transform::from_xyz(-110., 0., 1.).with_scale(Vec3::new(1., -1., 1.)),
+        ))
         .insert_resource(DebugPickingMode::Noisy)
         .insert_resource(Time::<Virtual>::from_max_delta(Duration::from_secs(5)))
         .insert_resource(Time::<Fixed>::from_hz(64.0 * 16.0))
         .insert_resource(stepping)
         .insert_resource(ImpulseCache::default())
-        .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.1)))
         .add_systems(Startup, (setup_camera, show_walls.after(Mel0nSetupSet)))
         .run();
 }
@@ -98,24 +129,27 @@ fn line_segment(start: Vec2, end: Vec2, thickness: f32, color: Color) -> impl Bu
     )
 }
 
-fn draw_velocities(query: Query<(&Velocity, &Transform), With<Fruit>>, mut gizmos: Gizmos) {
-    let mirror_y = vec2(1., -1.);
-    let cam_offset = vec2(-110.0, 0.);
-
+fn draw_velocities(
+    query: Query<(&Velocity, &Transform), With<Fruit>>,
+    space: ArenaCoords,
+    mut gizmos: Gizmos,
+) {
     for (vel, trans) in query {
         let pos = trans.translation.xy();
         gizmos.arrow_2d(
-            pos * mirror_y + cam_offset,
-            (pos + (vel.0 * 1.0)) * mirror_y + cam_offset,
+            space.to_world(pos),
+            space.to_world(pos + (vel.0 * 1.0)),
             RED,
         );
     }
 }
 
-fn draw_velocities_added(query: Query<(&Velocity), With<Fruit>>, mut gizmos: Gizmos) {
-    let mirror_y = vec2(1., -1.);
-    let cam_offset = vec2(-110.0, 0.);
-    let off = |v| v * mirror_y + cam_offset;
+fn draw_velocities_added(
+    query: Query<(&Velocity), With<Fruit>>,
+    space: ArenaCoords,
+    mut gizmos: Gizmos,
+) {
+    let off = |v| space.to_world(v);
 
     let velocities: Vec<_> = query.iter().collect();
 
@@ -135,11 +169,9 @@ struct ImpulseCache(Option<Vec<ImpulseGizmoEvent>>);
 fn draw_impulse_gizmos(
     mut ev_impulse: EventReader<ImpulseGizmoEvent>,
     mut imp_time: ResMut<ImpulseCache>,
+    space: ArenaCoords,
     mut gizmos: Gizmos,
 ) {
-    let mirror_y = vec2(1., -1.);
-    let cam_offset = vec2(-110.0, 0.);
-
     let vec = if ev_impulse.is_empty() {
         if let Some(vec) = imp_time.0.clone() {
             vec
@@ -153,8 +185,8 @@ fn draw_impulse_gizmos(
         impulses
         // for ImpulseGizmoEvent { pos, imp, mass } in impulses {
         //     gizmos.arrow_2d(
-        //         pos * mirror_y + cam_offset,
-        //         (pos + (imp / mass * 1000.0)) * mirror_y + cam_offset,
+        //         space.to_world(pos),
+        //         space.to_world(pos + (imp / mass * 1000.0)),
         //         GREEN,
         //     );
         // }
@@ -162,17 +194,15 @@ fn draw_impulse_gizmos(
 
     for ImpulseGizmoEvent { pos, imp, mass } in vec {
         gizmos.arrow_2d(
-            pos * mirror_y + cam_offset,
-            (pos + (imp / mass * 1.0)) * mirror_y + cam_offset,
+            space.to_world(pos),
+            space.to_world(pos + (imp / mass * 1.0)),
             GREEN,
         );
     }
 }
 
-fn angle_draw(query: Query<(&Transform), With<Fruit>>, mut gizmos: Gizmos) {
-    let mirror_y = vec2(1., -1.);
-    let cam_offset = vec2(-110.0, 0.);
-    let off = |v| v * mirror_y + cam_offset;
+fn angle_draw(query: Query<(&Transform), With<Fruit>>, space: ArenaCoords, mut gizmos: Gizmos) {
+    let off = |v| space.to_world(v);
 
     let mut fruits = query.iter();
     let Some(Transform { translation: a, .. }) = fruits.next() else {
@@ -198,23 +228,24 @@ fn angle_draw(query: Query<(&Transform), With<Fruit>>, mut gizmos: Gizmos) {
     // for (vel, trans) in query {
     //     let pos = trans.translation.xy();
     //     gizmos.arrow_2d(
-    //         pos * mirror_y + cam_offset,
-    //         (pos + (vel.0 * 10.0)) * mirror_y + cam_offset,
+    //         space.to_world(pos),
+    //         space.to_world(pos + (vel.0 * 10.0)),
     //         RED,
     //     );
     // }
 }
-fn draw_collision_count(query: Query<(&Collided, &Transform), With<Fruit>>, mut gizmos: Gizmos) {
-    let mirror_y = vec2(1., -1.);
-    let cam_offset = vec2(-110.0, 0.);
-
+fn draw_collision_count(
+    query: Query<(&Collided, &Transform, &Diameter), With<Fruit>>,
+    space: ArenaCoords,
+    mut gizmos: Gizmos,
+) {
     let rainbow = [RED, ORANGE_RED, YELLOW, GREEN, BLUE, PURPLE, HOT_PINK];
 
-    for (cold, trans) in query {
+    for (cold, trans, diameter) in query {
         let pos = trans.translation.xy();
         gizmos.circle_2d(
-            pos * mirror_y + cam_offset,
-            FRUIT_DIAMETER / 2.0,
+            space.to_world(pos),
+            diameter.0 / 2.0,
             rainbow[(cold.0 % 7) as usize],
         );
     }
@@ -240,30 +271,25 @@ fn setup_camera(mut commands: Commands) {
     ));
 }
 
+/// The player's arena and the ghost's.
+type ArenaRoots = Or<(With<Root>, With<GhostRoot>)>;
+
 fn show_walls(
     mut commands: Commands,
     query: Query<(&Wall, &Transform, &Collider)>,
-    root: Single<Entity, With<Root>>,
+    roots: Query<Entity, ArenaRoots>,
 ) {
-    let root = root.entity();
-
-    for (_, transform, collider) in query {
-        let horizontal = collider.half_size.y < collider.half_size.x;
-
-        let offset = if horizontal {
-            vec2(120.0 - 8.0, 0. - 8.0)
-        } else {
-            vec2(0.0 - 8.0, 74.0 - 8.0)
-        };
-        let entity = commands
-            .spawn((
-                Sprite::from_color(Color::linear_rgb(0.1, 0.1, 0.1), Vec2::ONE),
-                transform
-                    .with_scale((collider.half_size * 2.).extend(1.0))
-                    .with_translation(transform.translation + offset.extend(0.0)),
-            ))
-            .id();
-        commands.entity(root).add_child(entity);
+    // The ghost's arena gets walls too.
+    for root in &roots {
+        for (_, transform, collider) in &query {
+            let entity = commands
+                .spawn((
+                    Sprite::from_color(Color::linear_rgb(0.1, 0.1, 0.1), Vec2::ONE),
+                    transform.with_scale((collider.half_size * 2.).extend(1.0)),
+                ))
+                .id();
+            commands.entity(root).add_child(entity);
+        }
     }
 }
 //
@@ -0,0 +1,75 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*, window::PrimaryWindow};
+use mel0n::{
+    ArenaCoords,
+    fruit::Sandbox,
+    input::PendingInput,
+    mode::{GameState, not_paused},
+    replay::Playback,
+};
+
+/// Aims the dropper at the mouse or a finger. A left click drops; so does lifting the finger.
+/// F3 switches to the [`Sandbox`] instead, where fruit can be dragged about and thrown.
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                toggle_sandbox.run_if(input_just_pressed(KeyCode::F3)),
+                aim_pointer.run_if(
+                    in_state(GameState::Playing)
+                        .and(not_paused)
+                        .and(not(resource_exists::<Playback>))
+                        .and(not(in_sandbox)),
+                ),
+            ),
+        );
+    }
+}
+
+fn in_sandbox(sandbox: Res<Sandbox>) -> bool {
+    sandbox.0
+}
+
+fn toggle_sandbox(mut sandbox: ResMut<Sandbox>) {
+    sandbox.0 = !sandbox.0;
+    info!("Sandbox {}", if sandbox.0 { "on" } else { "off" });
+}
+
+fn aim_pointer(
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    space: ArenaCoords,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    mut last_cursor: Local<Option<Vec2>>,
+    mut pending: ResMut<PendingInput>,
+) {
+    let to_arena = |position: Vec2| {
+        let arena = space.viewport_to_arena(*camera, position)?;
+        #[allow(clippy::cast_possible_truncation)]
+        Some(arena.x.round() as i16)
+    };
+    let input = &mut pending.0;
+
+    // Only when the mouse moves, so it doesn't fight the keys.
+    let cursor = window.cursor_position();
+    if cursor != *last_cursor || mouse.just_pressed(MouseButton::Left) {
+        *last_cursor = cursor;
+        if let Some(aim) = cursor.and_then(to_arena) {
+            input.aim = Some(aim);
+            input.drop |= mouse.just_pressed(MouseButton::Left);
+        }
+    }
+
+    for touch in touches.iter() {
+        input.aim = to_arena(touch.position()).or(input.aim);
+    }
+    for touch in touches.iter_just_released() {
+        if let Some(aim) = to_arena(touch.position()) {
+            input.aim = Some(aim);
+            input.drop = true;
+        }
+    }
+}
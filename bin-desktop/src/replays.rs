@@ -0,0 +1,119 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use mel0n::{
+    ghost::{BestRun, BestRuns},
+    mode::GameMode,
+    replay::{PlayReplay, Recording, Replay, Unreplayable},
+};
+
+use crate::save::data_dir;
+
+/// Replays are saved in here, in [`data_dir`], named after when they were saved.
+const REPLAY_DIR: &str = "replays";
+
+/// F5 saves the current run (or the last one) as a replay. Start with `--replay <file>` to play
+/// one back. The best run for each mode is kept here too, for the ghost to race against.
+pub struct ReplayFilesPlugin;
+
+impl Plugin for ReplayFilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (load_replay_arg, load_best_runs))
+            .add_systems(Update, (save_replay, save_best_runs));
+    }
+}
+
+fn load_replay_arg(mut commands: Commands) {
+    let mut args = env::args().skip_while(|arg| arg != "--replay").skip(1);
+    let Some(path) = args.next() else {
+        return;
+    };
+    match read_replay(Path::new(&path)) {
+        Ok(replay) => commands.queue(PlayReplay(replay)),
+        Err(err) => error!("Couldn't load replay {path}: {err}"),
+    }
+}
+
+fn save_replay(
+    keys: Res<ButtonInput<KeyCode>>,
+    recording: Res<Recording>,
+    unreplayable: Res<Unreplayable>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+    if unreplayable.0 {
+        warn!("Fruit were moved in the sandbox, so this run can't be replayed");
+        return;
+    }
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let path = data_dir().join(REPLAY_DIR).join(format!("{secs}.ron"));
+    match write_replay(&path, &recording.0) {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(err) => error!("Couldn't save replay to {}: {err}", path.display()),
+    }
+}
+
+fn best_run_path(mode: GameMode) -> PathBuf {
+    let name = mode.name().to_lowercase().replace(' ', "-");
+    data_dir().join(REPLAY_DIR).join(format!("best-{name}.ron"))
+}
+
+fn load_best_runs(mut best: ResMut<BestRuns>) {
+    for mode in GameMode::ALL {
+        let path = best_run_path(mode);
+        if !path.exists() {
+            continue;
+        }
+        match read_best_run(&path) {
+            Ok(run) => {
+                best.submit(mode, run);
+            }
+            Err(err) => warn!("Couldn't load best run {}: {err}", path.display()),
+        }
+    }
+}
+
+fn save_best_runs(best: Res<BestRuns>) {
+    if !best.is_changed() || best.is_added() {
+        return;
+    }
+    for mode in GameMode::ALL {
+        let Some(run) = best.get(mode) else {
+            continue;
+        };
+        let path = best_run_path(mode);
+        let result = ron::to_string(run)
+            .map_err(Into::into)
+            .and_then(|ron| write_file(&path, &ron));
+        if let Err(err) = result {
+            error!("Couldn't save best run to {}: {err}", path.display());
+        }
+    }
+}
+
+fn read_best_run(path: &Path) -> Result<BestRun, Box<dyn std::error::Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn read_replay(path: &Path) -> Result<Replay, Box<dyn std::error::Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_replay(path: &Path, replay: &Replay) -> Result<(), Box<dyn std::error::Error>> {
+    write_file(path, &ron::to_string(replay)?)
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use directories::ProjectDirs;
+use mel0n::{
+    input::{Action, action_just_pressed},
+    mode::GameState,
+    save::{SaveBackend, SaveMedia, SavePlugin},
+    snapshot::{LoadSnapshot, Snapshot},
+};
+
+/// High scores, puzzle progress and the quicksave all live in here, in [`data_dir`].
+const SAVE_FILE: &str = "mel0n.sav";
+
+/// Where saves and replays are kept: the platform's data directory, or the working directory on
+/// platforms without one.
+pub fn data_dir() -> PathBuf {
+    ProjectDirs::from("", "", "mel0n").map_or_else(|| ".".into(), |dirs| dirs.data_dir().into())
+}
+
+/// A file standing in for cartridge save memory.
+#[derive(Debug, Clone)]
+pub struct FileSave {
+    path: PathBuf,
+}
+
+impl FileSave {
+    /// Plenty for a quicksave of a packed arena.
+    const LEN: usize = 1024 * 1024;
+}
+
+impl SaveBackend for FileSave {
+    type Error = io::Error;
+
+    fn capacity(&self) -> usize {
+        Self::LEN
+    }
+
+    /// Past the end of the file reads as zeroes, like memory that's never been written.
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> io::Result<()> {
+        buf.fill(0);
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        file.seek(SeekFrom::Start(offset as u64))?;
+        let mut filled = 0;
+        while filled < buf.len() {
+            match file.read(&mut buf[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.path)?;
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.write_all(data)
+    }
+}
+
+/// Keeps the save in [`SAVE_FILE`]. The quicksave key, F6 unless rebound, saves the run being
+/// played; F7 picks it back up.
+pub struct SaveFilesPlugin;
+
+impl Plugin for SaveFilesPlugin {
+    fn build(&self, app: &mut App) {
+        // The settings file has the settings and controls.
+        app.add_plugins(
+            SavePlugin::new(FileSave {
+                path: data_dir().join(SAVE_FILE),
+            })
+            .without_settings(),
+        )
+        .add_systems(
+            Update,
+            (
+                quicksave.run_if(
+                    in_state(GameState::Playing).and(action_just_pressed(Action::QuickSave)),
+                ),
+                quickload.run_if(input_just_pressed(KeyCode::F7)),
+            ),
+        );
+    }
+}
+
+fn quicksave(world: &mut World) {
+    let snapshot = Snapshot::capture(world);
+    let mut media = world.resource_mut::<SaveMedia<FileSave>>();
+    let stored = media.store_snapshot(&snapshot);
+    let path = media.0.path.display();
+    match stored {
+        Ok(true) => info!("Saved to {path}"),
+        Ok(false) => error!("Too much fruit to fit in {path}"),
+        Err(err) => error!("Couldn't save to {path}: {err}"),
+    }
+}
+
+fn quickload(mut commands: Commands, mut media: ResMut<SaveMedia<FileSave>>) {
+    let loaded = media.load_snapshot();
+    let path = media.0.path.display();
+    match loaded {
+        Ok(Some(snapshot)) => commands.queue(LoadSnapshot(snapshot)),
+        Ok(None) => info!("No quicksave in {path}"),
+        Err(err) => error!("Couldn't load from {path}: {err}"),
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use directories::ProjectDirs;
+use mel0n::{
+    bindings::{ButtonBindings, KeyBindings},
+    settings::Settings,
+};
+use serde::{Deserialize, Serialize};
+
+const MIN_WINDOW_SCALE: f32 = 0.5;
+const MAX_WINDOW_SCALE: f32 = 4.0;
+/// How much F9 and F10 shrink and grow the window by.
+const WINDOW_SCALE_STEP: f32 = 0.25;
+
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    fn toggled(self) -> Self {
+        match self {
+            Theme::Dark => Theme::Light,
+            Theme::Light => Theme::Dark,
+        }
+    }
+
+    fn background(self) -> Color {
+        match self {
+            Theme::Dark => Color::srgb(0.1, 0.1, 0.1),
+            Theme::Light => Color::srgb(0.9, 0.9, 0.85),
+        }
+    }
+}
+
+/// Scale factor for the whole window, on top of the monitor's.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct WindowScale(pub f32);
+
+/// Everything kept in the settings file. Anything missing from the file is left at its default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct SettingsFile {
+    game: Settings,
+    bindings: KeyBindings,
+    buttons: ButtonBindings,
+    theme: Theme,
+    window_scale: f32,
+}
+
+impl Default for SettingsFile {
+    fn default() -> Self {
+        Self {
+            game: Settings::default(),
+            bindings: KeyBindings::default(),
+            buttons: ButtonBindings::default(),
+            theme: Theme::default(),
+            window_scale: 1.0,
+        }
+    }
+}
+
+/// The settings as last read or written, so they're only written when something changes.
+#[derive(Resource)]
+struct SavedSettings {
+    path: Option<PathBuf>,
+    settings: SettingsFile,
+}
+
+/// Loads settings from `settings.ron` in the platform's config directory, and writes them back
+/// whenever they change. A missing or unreadable file just means the defaults. The game settings
+/// and controls are only kept here, not in the save file. F8 switches between the dark and light
+/// themes, and F9 and F10 shrink and grow the window.
+pub struct SettingsFilePlugin;
+
+impl Plugin for SettingsFilePlugin {
+    fn build(&self, app: &mut App) {
+        let path =
+            ProjectDirs::from("", "", "mel0n").map(|dirs| dirs.config_dir().join("settings.ron"));
+        let settings = match &path {
+            Some(path) => load_settings(path),
+            None => {
+                warn!("No config directory, so settings won't be kept");
+                SettingsFile::default()
+            }
+        };
+
+        app.insert_resource(settings.game)
+            .insert_resource(settings.bindings)
+            .insert_resource(settings.buttons)
+            .insert_resource(settings.theme)
+            .insert_resource(WindowScale(
+                settings
+                    .window_scale
+                    .clamp(MIN_WINDOW_SCALE, MAX_WINDOW_SCALE),
+            ))
+            .insert_resource(SavedSettings { path, settings })
+            .add_systems(
+                Update,
+                (
+                    change_look,
+                    apply_theme.run_if(resource_changed::<Theme>),
+                    apply_window_scale.run_if(resource_changed::<WindowScale>),
+                ),
+            )
+            .add_systems(Last, write_settings);
+    }
+}
+
+fn change_look(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut theme: ResMut<Theme>,
+    mut scale: ResMut<WindowScale>,
+) {
+    if keys.just_pressed(KeyCode::F8) {
+        *theme = theme.toggled();
+    }
+    let step = if keys.just_pressed(KeyCode::F9) {
+        -WINDOW_SCALE_STEP
+    } else if keys.just_pressed(KeyCode::F10) {
+        WINDOW_SCALE_STEP
+    } else {
+        return;
+    };
+    scale.0 = (scale.0 + step).clamp(MIN_WINDOW_SCALE, MAX_WINDOW_SCALE);
+}
+
+fn apply_theme(theme: Res<Theme>, mut clear_color: ResMut<ClearColor>) {
+    clear_color.0 = theme.background();
+}
+
+fn apply_window_scale(
+    scale: Res<WindowScale>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    window.resolution.set_scale_factor_override(Some(scale.0));
+}
+
+fn write_settings(
+    mut saved: ResMut<SavedSettings>,
+    settings: Res<Settings>,
+    bindings: Res<KeyBindings>,
+    buttons: Res<ButtonBindings>,
+    theme: Res<Theme>,
+    scale: Res<WindowScale>,
+) {
+    let current = SettingsFile {
+        game: *settings,
+        bindings: *bindings,
+        buttons: *buttons,
+        theme: *theme,
+        window_scale: scale.0,
+    };
+    if current == saved.settings {
+        return;
+    }
+    if let Some(path) = &saved.path {
+        match write_settings_file(path, &current) {
+            Ok(()) => info!("Saved settings to {}", path.display()),
+            Err(err) => error!("Couldn't save settings to {}: {err}", path.display()),
+        }
+    }
+    saved.settings = current;
+}
+
+/// The settings in `path`, or the defaults if there's no file there or it can't be read.
+fn load_settings(path: &Path) -> SettingsFile {
+    if !path.exists() {
+        return SettingsFile::default();
+    }
+    read_settings(path).unwrap_or_else(|err| {
+        warn!("Couldn't load settings from {}: {err}", path.display());
+        SettingsFile::default()
+    })
+}
+
+fn read_settings(path: &Path) -> Result<SettingsFile, Box<dyn std::error::Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_settings_file(
+    path: &Path,
+    settings: &SettingsFile,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(
+        path,
+        ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use super::{SettingsFile, Theme, load_settings, write_settings_file};
+
+    #[test]
+    fn missing_bad_and_partial_files_fall_back_to_defaults() {
+        let dir = env::temp_dir().join(format!("mel0n-settings-{}", process::id()));
+        let path = dir.join("settings.ron");
+        assert_eq!(load_settings(&path), SettingsFile::default());
+
+        let settings = SettingsFile {
+            theme: Theme::Light,
+            window_scale: 2.,
+            ..SettingsFile::default()
+        };
+        write_settings_file(&path, &settings).unwrap();
+        assert_eq!(load_settings(&path), settings);
+
+        fs::write(&path, "(theme: Light)").unwrap();
+        assert_eq!(
+            load_settings(&path),
+            SettingsFile {
+                theme: Theme::Light,
+                ..SettingsFile::default()
+            }
+        );
+
+        fs::write(&path, "(theme: Purple)").unwrap();
+        assert_eq!(load_settings(&path), SettingsFile::default());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
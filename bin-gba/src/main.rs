@@ -16,7 +16,7 @@ extern crate alloc;
 
 use log::info;
 use mel0n::{
-    agb,
+    agb::{self, save::MediaType},
     bevy::{
         app::PanicHandlerPlugin,
         diagnostic::{DiagnosticsPlugin, FrameCountPlugin},
@@ -28,13 +28,17 @@ use mel0n::{
         state::app::StatesPlugin,
         time::TimePlugin,
     },
-    bevy_mod_gba::{prelude::*, AgbSoundPlugin, Sprite},
+    bevy_mod_gba::{prelude::*, AgbSavePlugin, AgbSoundPlugin, Sprite},
     fruit::add_fruit,
     gba::{Mel0nGbaPlugin, Mel0nGbaSetupSet},
     wall::add_walls,
     Mel0nBasePlugin,
 };
 
+/// The save chip on the cartridge. Flash carts want `MediaType::Flash64K` or
+/// `MediaType::Flash128K`.
+const SAVE_MEDIA: MediaType = MediaType::Sram32K;
+
 /// Main entry point.
 #[expect(unsafe_code)]
 #[unsafe(export_name = "main")]
@@ -46,10 +50,16 @@ pub extern "C" fn main() {
     // This sets up integration between Bevy and the `agb` abstraction over the GameBoy Advance.
     // This _must_ be done first, as it also sets up `Instant` for us.
     // Otherwise, the `TimePlugin` will fail to initialize.
-    app.add_plugins(AgbPlugin.set(AgbSoundPlugin {
-        enable_dmg: true,
-        ..default()
-    }));
+    app.add_plugins(
+        AgbPlugin
+            .set(AgbSoundPlugin {
+                enable_dmg: true,
+                ..default()
+            })
+            .set(AgbSavePlugin {
+                save_type: Some(SAVE_MEDIA),
+            }),
+    );
 
     // Next we can add any Bevy plugins we like.
     // TODO: Used `DefaultPlugins` instead of this explicit list.
@@ -0,0 +1,272 @@
+//! Runs a scenario with no window and writes the score, the game state and where every fruit
+//! was and how fast it was going after each tick, for tuning the physics and looking into bug
+//! reports away from the game itself.
+//!
+//! ```sh
+//! bin-sim scenarios/drops.ron > drops.jsonl
+//! bin-sim --csv scenarios/drops.ron > drops.csv
+//! ```
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+use bevy::prelude::*;
+use mel0n::{
+    fruit::Tier,
+    headless::HeadlessConfig,
+    input::TickInput,
+    mode::GameMode,
+    physics::PhysicsConfig,
+    rules::PhysicsPreset,
+    testing::{FruitIds, SimApp},
+};
+use serde::{Deserialize, Serialize};
+
+/// How a run starts and what the player does in it, read from a RON file.
+#[derive(Debug, Clone, Deserialize)]
+struct Scenario {
+    #[serde(default)]
+    mode: GameMode,
+    #[serde(default)]
+    physics: PhysicsPreset,
+    /// Overrides for the solver's numbers. Any left out keep their usual values.
+    #[serde(default)]
+    physics_config: PhysicsConfig,
+    /// Fruit queue seed.
+    #[serde(default)]
+    seed: u32,
+    /// How many fixed ticks to run.
+    ticks: u32,
+    /// Fruit already in the arena when the run starts.
+    #[serde(default)]
+    fruit: Vec<StartingFruit>,
+    /// The tick input changes on, and what it changes to, as in a replay: each lasts until the
+    /// next, so a drop held down drops whenever the dropper is ready.
+    #[serde(default)]
+    input: Vec<(u32, TickInput)>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct StartingFruit {
+    tier: Tier,
+    /// Arena-space centre.
+    pos: Vec2,
+    #[serde(default)]
+    velocity: Vec2,
+}
+
+/// How the run stood after one tick, and one line of JSON output.
+#[derive(Debug, Clone, Serialize)]
+struct TickLine {
+    tick: u32,
+    score: u32,
+    state: String,
+    fruit: Vec<FruitLine>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct FruitLine {
+    /// From [`FruitIds`].
+    id: u32,
+    tier: u8,
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    JsonLines,
+    Csv,
+}
+
+const USAGE: &str = "usage: bin-sim [--csv] <scenario.ron>";
+
+fn main() -> ExitCode {
+    let mut format = Format::JsonLines;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--csv" => format = Format::Csv,
+            "--json" => format = Format::JsonLines,
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let scenario = match read_scenario(Path::new(&path)) {
+        Ok(scenario) => scenario,
+        Err(err) => {
+            eprintln!("Couldn't load scenario {path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let ticks = run(&scenario);
+    if let Err(err) = write_ticks(&mut BufWriter::new(io::stdout().lock()), &ticks, format) {
+        eprintln!("Couldn't write the trace: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn read_scenario(path: &Path) -> Result<Scenario, Box<dyn std::error::Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn run(scenario: &Scenario) -> Vec<TickLine> {
+    let mut sim = SimApp::new(HeadlessConfig {
+        mode: scenario.mode,
+        physics: scenario.physics,
+        physics_config: scenario.physics_config,
+        seed: Some(scenario.seed),
+        ..default()
+    });
+    for fruit in &scenario.fruit {
+        sim.spawn_fruit(fruit.tier, fruit.pos, fruit.velocity);
+    }
+    let mut changes = scenario.input.iter().peekable();
+    let mut input = TickInput::default();
+    let mut ids = FruitIds::default();
+    (0..scenario.ticks)
+        .map(|tick| {
+            while let Some((_, change)) = changes.next_if(|(at, _)| *at <= tick) {
+                input = *change;
+            }
+            sim.input(input);
+            sim.step(1);
+            TickLine {
+                tick,
+                score: sim.score(),
+                state: format!("{:?}", sim.state()),
+                fruit: sim
+                    .fruits()
+                    .into_iter()
+                    .map(|fruit| FruitLine {
+                        id: ids.of(fruit.entity),
+                        tier: fruit.tier.0,
+                        x: fruit.pos.x,
+                        y: fruit.pos.y,
+                        vx: fruit.velocity.x,
+                        vy: fruit.velocity.y,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+fn write_ticks(out: &mut impl Write, ticks: &[TickLine], format: Format) -> io::Result<()> {
+    match format {
+        Format::Csv => out.write_all(to_csv(ticks).as_bytes())?,
+        Format::JsonLines => {
+            for line in ticks {
+                serde_json::to_writer(&mut *out, line)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// One line per fruit per tick, under a header. A tick with no fruit still gets a line, with
+/// the fruit's columns left empty, so the score and state are there for every tick.
+fn to_csv(ticks: &[TickLine]) -> String {
+    let mut csv = String::from("tick,score,state,fruit,tier,x,y,vx,vy\n");
+    for tick in ticks {
+        let run = format!("{},{},{}", tick.tick, tick.score, tick.state);
+        if tick.fruit.is_empty() {
+            csv.push_str(&run);
+            csv.push_str(",,,,,,\n");
+        }
+        for fruit in &tick.fruit {
+            // Writing to a `String` can't fail.
+            let _ = writeln!(
+                csv,
+                "{run},{},{},{},{},{},{}",
+                fruit.id, fruit.tier, fruit.x, fruit.y, fruit.vx, fruit.vy
+            );
+        }
+    }
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::{Format, FruitLine, TickLine, read_scenario, run, to_csv, write_ticks};
+
+    #[test]
+    fn example_scenarios_run() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let scenario = read_scenario(&path).unwrap();
+            let ticks = run(&scenario);
+            assert_eq!(ticks.len(), scenario.ticks as usize);
+            let last = ticks.last().unwrap();
+            assert!(
+                !last.fruit.is_empty(),
+                "{} ended with an empty arena",
+                path.display()
+            );
+            assert_eq!(last.state, "Playing");
+
+            let mut json = Vec::new();
+            write_ticks(&mut json, &ticks, Format::JsonLines).unwrap();
+            let lines: Vec<_> = String::from_utf8(json)
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect();
+            assert_eq!(lines.len(), ticks.len());
+            let json: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+            assert_eq!(json["tick"], scenario.ticks - 1);
+            assert_eq!(json["score"], last.score);
+            assert!(json["fruit"][0]["vy"].is_number());
+        }
+    }
+
+    #[test]
+    fn csv_keeps_ticks_with_no_fruit() {
+        let ticks: Vec<_> = (0..2)
+            .map(|tick| TickLine {
+                tick,
+                score: 5,
+                state: "GameOver".into(),
+                fruit: if tick == 0 {
+                    vec![FruitLine {
+                        id: 0,
+                        tier: 2,
+                        x: 1.5,
+                        y: 2.,
+                        vx: -0.25,
+                        vy: 3.,
+                    }]
+                } else {
+                    Vec::new()
+                },
+            })
+            .collect();
+        assert_eq!(
+            to_csv(&ticks),
+            "tick,score,state,fruit,tier,x,y,vx,vy\n\
+             0,5,GameOver,0,2,1.5,2,-0.25,3\n\
+             1,5,GameOver,,,,,,\n"
+        );
+    }
+}
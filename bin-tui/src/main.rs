@@ -0,0 +1,289 @@
+//! Plays the game in a terminal, over SSH or anywhere else without a window. The arena is drawn
+//! in coloured half-block characters, two square pixels to a cell, scaled to fit.
+//!
+//! ```sh
+//! bin-tui [classic|zen|time-attack]
+//! ```
+
+mod render;
+
+use std::{
+    env,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    queue,
+    style::{self, Stylize},
+    terminal,
+};
+use mel0n::{
+    dropper::Dropper,
+    headless::{HeadlessApp, HeadlessConfig},
+    input::TickInput,
+    mode::{GameMode, GameState},
+    rules::GameRules,
+};
+
+use crate::render::Picture;
+
+/// Fixed tick, as on the GBA.
+const TICK: Duration = Duration::from_micros(16_667);
+/// How often the screen is redrawn.
+const FRAME: Duration = Duration::from_millis(33);
+/// Without key release events, a press of left or right keeps steering this long, until the
+/// terminal's key repeat takes over.
+const STEER_FOR: Duration = Duration::from_millis(150);
+/// Modes whose fruit come from a seed, rather than puzzles or the date.
+const MODES: [GameMode; 3] = [GameMode::Classic, GameMode::Zen, GameMode::TimeAttack];
+
+const USAGE: &str = "usage: bin-tui [classic|zen|time-attack]";
+
+/// Puts the terminal back as it was however the game ends.
+struct RawTerminal {
+    enhanced: bool,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        let mut out = io::stdout();
+        queue!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        if enhanced {
+            queue!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        out.flush()?;
+        Ok(Self { enhanced })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.enhanced {
+            let _ = queue!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = queue!(out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = out.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The controls since the last tick.
+#[derive(Debug, Default)]
+struct Controls {
+    dir: i8,
+    /// When steering stops, if the terminal won't say when the key comes up.
+    steer_until: Option<Instant>,
+    drop: bool,
+    hold: bool,
+    pause: bool,
+    restart: bool,
+    quit: bool,
+}
+
+impl Controls {
+    fn key(&mut self, key: KeyEvent, enhanced: bool) {
+        let pressed = key.kind != KeyEventKind::Release;
+        match key.code {
+            KeyCode::Left | KeyCode::Char('a' | 'h') => self.steer(-1, pressed, enhanced),
+            KeyCode::Right | KeyCode::Char('d' | 'l') => self.steer(1, pressed, enhanced),
+            _ if !pressed => {}
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+            }
+            KeyCode::Char(' ') | KeyCode::Down | KeyCode::Char('s' | 'j') => self.drop = true,
+            KeyCode::Up | KeyCode::Char('c' | 'k') => self.hold = true,
+            KeyCode::Char('p') => self.pause = true,
+            KeyCode::Char('r') => self.restart = true,
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => {}
+        }
+    }
+
+    fn steer(&mut self, dir: i8, pressed: bool, enhanced: bool) {
+        if pressed {
+            self.dir = dir;
+            self.steer_until = (!enhanced).then(|| Instant::now() + STEER_FOR);
+        } else if self.dir == dir {
+            self.dir = 0;
+        }
+    }
+
+    /// The input for the next tick. Drops and holds only count once.
+    fn tick_input(&mut self, now: Instant) -> TickInput {
+        if self.steer_until.is_some_and(|until| now >= until) {
+            self.dir = 0;
+            self.steer_until = None;
+        }
+        TickInput {
+            dir: self.dir,
+            drop: std::mem::take(&mut self.drop),
+            hold: std::mem::take(&mut self.hold),
+            aim: None,
+        }
+    }
+}
+
+fn new_run(mode: GameMode) -> HeadlessApp {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.subsec_nanos());
+    HeadlessApp::new(HeadlessConfig {
+        mode,
+        seed: Some(seed),
+        tick: TICK,
+        ..default()
+    })
+}
+
+fn main() -> ExitCode {
+    let mode = match env::args().nth(1) {
+        None => GameMode::Classic,
+        Some(arg) => {
+            let found = MODES
+                .into_iter()
+                .find(|mode| mode.name().to_lowercase().replace(' ', "-") == arg);
+            let Some(mode) = found else {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            mode
+        }
+    };
+    if let Err(err) = play(mode) {
+        eprintln!("Terminal trouble: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn play(mode: GameMode) -> io::Result<()> {
+    let raw = RawTerminal::enter()?;
+    let mut out = BufWriter::new(io::stdout());
+    let mut sim = new_run(mode);
+    let mut controls = Controls::default();
+    let mut next_tick = Instant::now();
+
+    loop {
+        let frame_end = Instant::now() + FRAME;
+        while let Some(wait) = frame_end.checked_duration_since(Instant::now()) {
+            if !event::poll(wait)? {
+                break;
+            }
+            match event::read()? {
+                Event::Key(key) => controls.key(key, raw.enhanced),
+                Event::Resize(..) => queue!(out, terminal::Clear(terminal::ClearType::All))?,
+                _ => {}
+            }
+        }
+        if controls.quit {
+            return Ok(());
+        }
+        if std::mem::take(&mut controls.restart) && sim.state() != GameState::Playing {
+            sim = new_run(mode);
+        }
+        if std::mem::take(&mut controls.pause) && sim.state() == GameState::Playing {
+            sim.set_paused(!sim.paused());
+        }
+
+        let now = Instant::now();
+        if sim.paused() {
+            // Nothing pressed while paused carries over, and no ticks are owed for the wait.
+            controls.tick_input(now);
+            next_tick = now;
+        }
+        // Skip ahead rather than race to catch up after a stall.
+        if now.duration_since(next_tick) > FRAME * 4 {
+            next_tick = now;
+        }
+        while next_tick <= now {
+            sim.input(controls.tick_input(now));
+            sim.step(1);
+            next_tick += TICK;
+        }
+
+        draw(&mut out, &mut sim, mode)?;
+    }
+}
+
+fn draw(out: &mut impl Write, sim: &mut HeadlessApp, mode: GameMode) -> io::Result<()> {
+    const PANEL_WIDTH: u16 = 24;
+    let (cols, rows) = terminal::size()?;
+    let mut picture = Picture::fitting(cols.saturating_sub(PANEL_WIDTH), rows);
+    picture.draw(sim);
+
+    queue!(out, cursor::MoveTo(0, 0))?;
+    for row in 0..picture.height.div_ceil(2) {
+        queue!(
+            out,
+            cursor::MoveTo(0, u16::try_from(row).unwrap_or(u16::MAX))
+        )?;
+        for x in 0..picture.width {
+            let top = picture.pixel(x, row * 2).color();
+            let bottom = picture.pixel(x, row * 2 + 1).color();
+            let cell = match (top, bottom) {
+                (None, None) => " ".stylize(),
+                (None, Some(bottom)) => "▄".with(rgb(bottom)),
+                (Some(top), None) => "▀".with(rgb(top)),
+                (Some(top), Some(bottom)) => "▀".with(rgb(top)).on(rgb(bottom)),
+            };
+            queue!(out, style::PrintStyledContent(cell))?;
+        }
+    }
+
+    let dropper = sim.world().resource::<Dropper>();
+    let hold = sim.world().resource::<GameRules>().hold;
+    let mut lines = vec![
+        format!("mel0n: {}", mode.name()),
+        String::new(),
+        format!("Score  {}", sim.score()),
+        format!("Next   {}", dropper.next.name()),
+    ];
+    if hold {
+        let held = dropper.held.map_or("-", |tier| tier.name());
+        lines.push(format!("Held   {held}"));
+    }
+    lines.extend([
+        String::new(),
+        match sim.state() {
+            GameState::Playing if sim.paused() => "Paused, p to carry on".into(),
+            GameState::Playing => String::new(),
+            _ => "Game over! r to go again".into(),
+        },
+        String::new(),
+        "←/→  move".into(),
+        "␣/↓  drop".into(),
+    ]);
+    if hold {
+        lines.push("c/↑  hold".into());
+    }
+    lines.extend(["p    pause".into(), "q    quit".into()]);
+    let left = u16::try_from(picture.width + 2).unwrap_or(u16::MAX);
+    for (row, line) in (0..).zip(lines) {
+        queue!(
+            out,
+            cursor::MoveTo(left, row),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+            style::Print(line)
+        )?;
+    }
+    out.flush()
+}
+
+fn rgb(color: Srgba) -> style::Color {
+    let [r, g, b, _] = color.to_u8_array();
+    style::Color::Rgb { r, g, b }
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use mel0n::{
+    dropper::Dropper,
+    fruit::{TIER_DIAMETERS, Tier},
+    headless::HeadlessApp,
+    overflow::DANGER_LINE,
+    wall::{BOTTOM_WALL, LEFT_WALL, RIGHT_WALL, TOP_WALL, WALL_THICKNESS},
+};
+
+/// What's at one spot in the arena, as far as drawing it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pixel {
+    Empty,
+    Wall,
+    DangerLine,
+    Fruit(Tier),
+    /// The fruit waiting in the dropper.
+    Dropper(Tier),
+}
+
+impl Pixel {
+    /// Coloured by tier from red round the colour wheel, as on the desktop. The fruit in the
+    /// dropper is a darker shade of its colour.
+    pub fn color(self) -> Option<Srgba> {
+        let tier_hue = |tier: Tier| {
+            let tiers = TIER_DIAMETERS.len();
+            300. * f32::from(tier.0) / (tiers - 1) as f32
+        };
+        let color = match self {
+            Pixel::Empty => return None,
+            Pixel::Wall => Color::srgb(0.6, 0.6, 0.6),
+            Pixel::DangerLine => Color::srgb(0.4, 0.1, 0.1),
+            Pixel::Fruit(tier) => Color::hsl(tier_hue(tier), 0.8, 0.6),
+            Pixel::Dropper(tier) => Color::hsl(tier_hue(tier), 0.6, 0.3),
+        };
+        Some(color.to_srgba())
+    }
+}
+
+/// The arena sampled onto a grid of square pixels, each half a terminal cell. Each pixel is
+/// `scale` arena units across, with a border of one pixel for the walls however thin they are.
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub width: usize,
+    pub height: usize,
+    pub scale: f32,
+    /// The arena's top left corner, less the pixel for the wall. Rounding leaves some slack
+    /// between the walls, which is shared between both sides.
+    origin: Vec2,
+    pixels: Vec<Pixel>,
+}
+
+impl Picture {
+    /// The largest picture of the arena that fits in `cols` by `rows` terminal cells.
+    pub fn fitting(cols: u16, rows: u16) -> Self {
+        let (arena_w, arena_h) = (RIGHT_WALL - LEFT_WALL, BOTTOM_WALL - TOP_WALL);
+        // A pixel for each wall at the sides, one for the floor.
+        let cols = f32::from(cols.max(3) - 2);
+        let rows = f32::from(rows.max(2)) * 2. - 1.;
+        let scale = (arena_w / cols).max(arena_h / rows);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (width, height) = (
+            (arena_w / scale).ceil() as usize + 2,
+            (arena_h / scale).ceil() as usize + 1,
+        );
+        #[allow(clippy::cast_precision_loss)]
+        let slack = (width - 2) as f32 * scale - arena_w;
+        Self {
+            width,
+            height,
+            scale,
+            origin: vec2(LEFT_WALL - scale - slack / 2., TOP_WALL),
+            pixels: vec![Pixel::Empty; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Pixel {
+        self.pixels
+            .get(y * self.width + x)
+            .copied()
+            .unwrap_or(Pixel::Empty)
+    }
+
+    /// Where the centre of a pixel is in the arena.
+    fn arena_pos(&self, x: usize, y: usize) -> Vec2 {
+        #[allow(clippy::cast_precision_loss)]
+        let (x, y) = (x as f32, y as f32);
+        self.origin + vec2(x + 0.5, y + 0.5) * self.scale
+    }
+
+    /// Samples the arena as `sim` has it now. Later fruit are drawn over earlier ones.
+    pub fn draw(&mut self, sim: &mut HeadlessApp) {
+        let fruits = sim.fruits();
+        let dropper = sim.world().resource::<Dropper>();
+        let waiting = dropper
+            .ready
+            .then(|| (dropper.spawn_pos(), dropper.current));
+        let thickness = WALL_THICKNESS.max(self.scale / 2.);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = self.arena_pos(x, y);
+                let inside =
+                    |(centre, diameter): (Vec2, f32)| pos.distance(centre) <= diameter / 2.;
+                let fruit = fruits
+                    .iter()
+                    .rev()
+                    .find(|fruit| inside((fruit.pos, fruit.diameter)));
+                self.pixels[y * self.width + x] =
+                    if pos.x < LEFT_WALL || pos.x > RIGHT_WALL || pos.y > BOTTOM_WALL {
+                        Pixel::Wall
+                    } else if let Some(fruit) = fruit {
+                        Pixel::Fruit(fruit.tier)
+                    } else if let Some((_, tier)) =
+                        waiting.filter(|&(at, tier)| inside((at, tier.diameter())))
+                    {
+                        Pixel::Dropper(tier)
+                    } else if (pos.y - DANGER_LINE).abs() < thickness {
+                        Pixel::DangerLine
+                    } else {
+                        Pixel::Empty
+                    };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+    use mel0n::{
+        fruit::Tier,
+        headless::HeadlessConfig,
+        mode::GameMode,
+        testing::SimApp,
+        wall::{BOTTOM_WALL, LEFT_WALL, RIGHT_WALL},
+    };
+
+    use super::{Picture, Pixel};
+
+    #[test]
+    fn fruit_and_walls_land_where_they_are() {
+        let mut sim = SimApp::new(HeadlessConfig {
+            mode: GameMode::Zen,
+            seed: Some(1),
+            ..default()
+        });
+        let tier = Tier(6);
+        let centre = vec2(
+            f32::midpoint(LEFT_WALL, RIGHT_WALL),
+            BOTTOM_WALL - tier.diameter() / 2.,
+        );
+        sim.spawn_fruit(tier, centre, Vec2::ZERO);
+
+        let mut picture = Picture::fitting(80, 24);
+        assert!(picture.width <= 80 && picture.height <= 48);
+        picture.draw(&mut sim);
+
+        let last = picture.height - 1;
+        assert_eq!(picture.pixel(0, 20), Pixel::Wall);
+        assert_eq!(picture.pixel(picture.width - 1, 20), Pixel::Wall);
+        assert_eq!(picture.pixel(picture.width - 2, 20), Pixel::Empty);
+        assert_eq!(picture.pixel(picture.width / 2, last), Pixel::Wall);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let above_floor = last - (tier.diameter() / 2. / picture.scale) as usize;
+        assert_eq!(
+            picture.pixel(picture.width / 2, above_floor),
+            Pixel::Fruit(tier)
+        );
+        assert_eq!(picture.pixel(2, above_floor), Pixel::Empty);
+    }
+}
@@ -0,0 +1,365 @@
+use bevy::{input::InputSystem, prelude::*};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{Action, ActionState, map_actions},
+    mode::GameState,
+};
+
+/// Turns the controls into [`Action`]s, and lets players change them.
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "desktop")]
+        app.init_resource::<KeyBindings>();
+
+        app.init_resource::<ActionState>()
+            .init_resource::<ButtonBindings>()
+            .init_resource::<ControlsMenu>()
+            .add_systems(PreUpdate, map_actions.after(InputSystem))
+            .add_systems(Update, controls_menu.run_if(in_state(GameState::Controls)))
+            .add_systems(OnEnter(GameState::Controls), open_controls_menu);
+    }
+}
+
+/// The action already on `control` that `action` can't share it with, if any.
+fn clash<T: PartialEq>(action: Action, control: T, bound: impl Fn(Action) -> T) -> Option<Action> {
+    Action::ALL
+        .into_iter()
+        .find(|&other| action.clashes_with(other) && bound(other) == control)
+}
+
+/// Gamepad controls. The GBA only offers the [`PRESETS`](Self::PRESETS), as it has so few
+/// buttons.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ButtonBindings {
+    pub left: GamepadButton,
+    pub right: GamepadButton,
+    pub up: GamepadButton,
+    pub down: GamepadButton,
+    pub drop: GamepadButton,
+    pub hold: GamepadButton,
+    pub pause: GamepadButton,
+    pub confirm: GamepadButton,
+    pub back: GamepadButton,
+    pub quick_save: GamepadButton,
+}
+
+impl Default for ButtonBindings {
+    fn default() -> Self {
+        Self::PRESETS[0].1
+    }
+}
+
+impl ButtonBindings {
+    /// Layouts named the GBA way: East is A and South is B.
+    pub const PRESETS: [(&'static str, ButtonBindings); 2] = [
+        (
+            "A drops, B holds",
+            ButtonBindings {
+                left: GamepadButton::DPadLeft,
+                right: GamepadButton::DPadRight,
+                up: GamepadButton::DPadUp,
+                down: GamepadButton::DPadDown,
+                drop: GamepadButton::East,
+                hold: GamepadButton::South,
+                pause: GamepadButton::Start,
+                confirm: GamepadButton::East,
+                back: GamepadButton::South,
+                quick_save: GamepadButton::Select,
+            },
+        ),
+        (
+            "B drops, A holds",
+            ButtonBindings {
+                left: GamepadButton::DPadLeft,
+                right: GamepadButton::DPadRight,
+                up: GamepadButton::DPadUp,
+                down: GamepadButton::DPadDown,
+                drop: GamepadButton::South,
+                hold: GamepadButton::East,
+                pause: GamepadButton::Start,
+                confirm: GamepadButton::East,
+                back: GamepadButton::South,
+                quick_save: GamepadButton::Select,
+            },
+        ),
+    ];
+
+    #[must_use]
+    pub fn button(&self, action: Action) -> GamepadButton {
+        match action {
+            Action::MoveLeft => self.left,
+            Action::MoveRight => self.right,
+            Action::MoveUp => self.up,
+            Action::MoveDown => self.down,
+            Action::Drop => self.drop,
+            Action::Hold => self.hold,
+            Action::Pause => self.pause,
+            Action::Confirm => self.confirm,
+            Action::Back => self.back,
+            Action::QuickSave => self.quick_save,
+        }
+    }
+
+    fn button_mut(&mut self, action: Action) -> &mut GamepadButton {
+        match action {
+            Action::MoveLeft => &mut self.left,
+            Action::MoveRight => &mut self.right,
+            Action::MoveUp => &mut self.up,
+            Action::MoveDown => &mut self.down,
+            Action::Drop => &mut self.drop,
+            Action::Hold => &mut self.hold,
+            Action::Pause => &mut self.pause,
+            Action::Confirm => &mut self.confirm,
+            Action::Back => &mut self.back,
+            Action::QuickSave => &mut self.quick_save,
+        }
+    }
+
+    /// Puts `action` on `button`, unless that clashes with another action.
+    ///
+    /// # Errors
+    ///
+    /// The action `button` is already used for.
+    pub fn rebind(&mut self, action: Action, button: GamepadButton) -> Result<(), Action> {
+        if let Some(other) = clash(action, button, |other| self.button(other)) {
+            return Err(other);
+        }
+        *self.button_mut(action) = button;
+        Ok(())
+    }
+
+    /// Which of the [`PRESETS`](Self::PRESETS) these are, if any.
+    #[must_use]
+    pub fn preset(&self) -> Option<usize> {
+        Self::PRESETS.iter().position(|(_, preset)| preset == self)
+    }
+}
+
+/// Keyboard controls.
+#[cfg(feature = "desktop")]
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct KeyBindings {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub drop: KeyCode,
+    pub hold: KeyCode,
+    pub pause: KeyCode,
+    pub confirm: KeyCode,
+    pub back: KeyCode,
+    pub quick_save: KeyCode,
+}
+
+#[cfg(feature = "desktop")]
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: KeyCode::ArrowLeft,
+            right: KeyCode::ArrowRight,
+            up: KeyCode::ArrowUp,
+            down: KeyCode::ArrowDown,
+            drop: KeyCode::Space,
+            hold: KeyCode::KeyC,
+            pause: KeyCode::Escape,
+            confirm: KeyCode::Space,
+            back: KeyCode::Escape,
+            quick_save: KeyCode::F6,
+        }
+    }
+}
+
+#[cfg(feature = "desktop")]
+impl KeyBindings {
+    #[must_use]
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::MoveLeft => self.left,
+            Action::MoveRight => self.right,
+            Action::MoveUp => self.up,
+            Action::MoveDown => self.down,
+            Action::Drop => self.drop,
+            Action::Hold => self.hold,
+            Action::Pause => self.pause,
+            Action::Confirm => self.confirm,
+            Action::Back => self.back,
+            Action::QuickSave => self.quick_save,
+        }
+    }
+
+    fn key_mut(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::MoveLeft => &mut self.left,
+            Action::MoveRight => &mut self.right,
+            Action::MoveUp => &mut self.up,
+            Action::MoveDown => &mut self.down,
+            Action::Drop => &mut self.drop,
+            Action::Hold => &mut self.hold,
+            Action::Pause => &mut self.pause,
+            Action::Confirm => &mut self.confirm,
+            Action::Back => &mut self.back,
+            Action::QuickSave => &mut self.quick_save,
+        }
+    }
+
+    /// Puts `action` on `key`, unless that clashes with another action.
+    ///
+    /// # Errors
+    ///
+    /// The action `key` is already used for.
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        if let Some(other) = clash(action, key, |other| self.key(other)) {
+            return Err(other);
+        }
+        *self.key_mut(action) = key;
+        Ok(())
+    }
+}
+
+/// Where the controls menu is up to.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct ControlsMenu {
+    pub selected: Action,
+    /// Waiting for the next key or button, to put the selected action on.
+    pub capturing: bool,
+    /// The action the last key or button tried was already used for.
+    pub clash: Option<Action>,
+}
+
+fn open_controls_menu(mut menu: ResMut<ControlsMenu>) {
+    *menu = ControlsMenu::default();
+}
+
+/// Left and right pick a preset layout.
+#[cfg(not(feature = "desktop"))]
+pub fn controls_menu(
+    actions: Res<ActionState>,
+    mut buttons: ResMut<ButtonBindings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let presets = ButtonBindings::PRESETS.len();
+    let current = buttons.preset().unwrap_or(0);
+    let step = if actions.just_pressed(Action::MoveRight) {
+        1
+    } else if actions.just_pressed(Action::MoveLeft) {
+        presets - 1
+    } else {
+        if actions.just_pressed(Action::Confirm) || actions.just_pressed(Action::Back) {
+            next_state.set(GameState::Title);
+        }
+        return;
+    };
+    *buttons = ButtonBindings::PRESETS[(current + step) % presets].1;
+}
+
+/// Up and down pick an action, confirm waits for the key or button to put it on.
+#[cfg(feature = "desktop")]
+pub fn controls_menu(
+    actions: Res<ActionState>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Option<Single<&Gamepad>>,
+    mut menu: ResMut<ControlsMenu>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut buttons: ResMut<ButtonBindings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if menu.capturing {
+        let result = if let Some(&key) = keys.get_just_pressed().next() {
+            key_bindings.rebind(menu.selected, key)
+        } else if let Some(&button) = gamepad.as_ref().and_then(|g| g.get_just_pressed().next()) {
+            buttons.rebind(menu.selected, button)
+        } else {
+            return;
+        };
+        menu.capturing = false;
+        menu.clash = result.err();
+        return;
+    }
+
+    let len = Action::ALL.len();
+    let selected = menu.selected.index();
+    if actions.just_pressed(Action::MoveDown) {
+        menu.selected = Action::ALL[(selected + 1) % len];
+        menu.clash = None;
+    } else if actions.just_pressed(Action::MoveUp) {
+        menu.selected = Action::ALL[(selected + len - 1) % len];
+        menu.clash = None;
+    } else if actions.just_pressed(Action::Confirm) {
+        menu.capturing = true;
+        menu.clash = None;
+    } else if actions.just_pressed(Action::Back) {
+        next_state.set(GameState::Title);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{prelude::*, state::app::StatesPlugin};
+
+    use super::ButtonBindings;
+    use crate::{
+        input::{Action, ActionState},
+        mode::{GameMode, GameState, title_menu},
+    };
+
+    #[test]
+    pub fn clashing_buttons_are_refused() {
+        let mut buttons = ButtonBindings::default();
+        assert_eq!(
+            buttons.rebind(Action::Drop, GamepadButton::South),
+            Err(Action::Hold)
+        );
+        assert_eq!(buttons, ButtonBindings::default());
+
+        // Confirm is only used in menus, where drop isn't.
+        assert_eq!(buttons.rebind(Action::Drop, GamepadButton::North), Ok(()));
+        assert_eq!(
+            buttons.rebind(Action::Confirm, GamepadButton::North),
+            Ok(())
+        );
+        assert_eq!(buttons.preset(), None);
+    }
+
+    #[test]
+    pub fn presets_have_no_clashes() {
+        for (name, preset) in ButtonBindings::PRESETS {
+            for action in Action::ALL {
+                let mut rebound = preset;
+                assert_eq!(
+                    rebound.rebind(action, preset.button(action)),
+                    Ok(()),
+                    "{name}: {}",
+                    action.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn each_preset_starts_a_run_from_the_title() {
+        for (name, preset) in ButtonBindings::PRESETS {
+            let mut app = App::new();
+            app.add_plugins(StatesPlugin)
+                .init_state::<GameState>()
+                .init_resource::<ActionState>()
+                .insert_resource(GameMode::Classic)
+                .add_systems(Update, title_menu.run_if(in_state(GameState::Title)));
+            app.world_mut()
+                .resource_mut::<ActionState>()
+                .update(|action| preset.button(action) == preset.confirm);
+            app.update();
+            app.update();
+            assert_eq!(
+                *app.world().resource::<State<GameState>>().get(),
+                GameState::Playing,
+                "{name}"
+            );
+        }
+    }
+}
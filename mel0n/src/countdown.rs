@@ -0,0 +1,80 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::mode::GameState;
+
+/// Time left in a timed run, or `None` if the mode has no time limit.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Countdown(pub Option<Duration>);
+
+/// Runs on the fixed clock, so it stops whenever the game is paused and stays in step with
+/// replays.
+pub fn tick_countdown(
+    mut countdown: ResMut<Countdown>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(remaining) = countdown.0 else {
+        return;
+    };
+    let remaining = remaining.saturating_sub(time.delta());
+    countdown.0 = Some(remaining);
+
+    if remaining.is_zero() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use bevy::{
+        prelude::*,
+        state::app::StatesPlugin,
+        time::{TimePlugin, TimeUpdateStrategy},
+    };
+
+    use super::{Countdown, tick_countdown};
+    use crate::mode::GameState;
+
+    #[test]
+    pub fn countdown_stops_while_paused() {
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), TimePlugin, StatesPlugin))
+            .init_state::<GameState>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_resource(Countdown(Some(Duration::from_millis(300))))
+            .add_systems(Update, tick_countdown);
+
+        // The first update only starts the clock.
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<Countdown>().0,
+            Some(Duration::from_millis(200))
+        );
+
+        app.world_mut().resource_mut::<Time<Virtual>>().pause();
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<Countdown>().0,
+            Some(Duration::from_millis(200))
+        );
+
+        app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<Countdown>().0, Some(Duration::ZERO));
+        // State changes land on the next update.
+        app.update();
+        assert_eq!(
+            *app.world().resource::<State<GameState>>(),
+            GameState::GameOver
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use alloc::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+#[cfg(feature = "gba")]
+use crate::{
+    input::{Action, ActionState},
+    mode::GameState,
+};
+
+/// A calendar date, for seeding the daily challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Default for Date {
+    /// Where the GBA date menu starts, as there's no clock to read.
+    fn default() -> Self {
+        Self {
+            year: 2025,
+            month: 1,
+            day: 1,
+        }
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    /// `None` if there's no such day.
+    #[must_use]
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day))
+            .then_some(Self { year, month, day })
+    }
+
+    /// The date `days` after 1970-01-01.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_unix_days(days: u32) -> Self {
+        // Howard Hinnant's `civil_from_days`, with years starting in March so the leap day comes
+        // last.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let march_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * march_month + 2) / 5 + 1) as u8;
+        let month = if march_month < 10 {
+            march_month + 3
+        } else {
+            march_month - 9
+        } as u8;
+        let year = (year_of_era + era * 400 + u32::from(month <= 2)) as u16;
+        Self { year, month, day }
+    }
+
+    /// The fruit queue seed for this day. Neighbouring days get unrelated seeds.
+    #[must_use]
+    pub fn seed(self) -> u32 {
+        let mut x = u32::from(self.year) << 9 | u32::from(self.month) << 5 | u32::from(self.day);
+        // Murmur3's finaliser.
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x85eb_ca6b);
+        x ^= x >> 13;
+        x = x.wrapping_mul(0xc2b2_ae35);
+        x ^= x >> 16;
+        x
+    }
+
+    /// Moves `field` one step, wrapping within its range. The day is pulled back if the month
+    /// ends up too short for it.
+    pub fn step(&mut self, field: DateField, forward: bool) {
+        let wrap = |value: u8, max: u8| match (forward, value) {
+            (true, v) if v >= max => 1,
+            (true, v) => v + 1,
+            (false, v) if v <= 1 => max,
+            (false, v) => v - 1,
+        };
+        match field {
+            DateField::Year if forward => self.year = self.year.saturating_add(1),
+            DateField::Year => self.year = self.year.saturating_sub(1),
+            DateField::Month => self.month = wrap(self.month, 12),
+            DateField::Day => self.day = wrap(self.day, days_in_month(self.year, self.month)),
+        }
+        self.day = self.day.min(days_in_month(self.year, self.month));
+    }
+}
+
+/// The date the daily challenge is played for.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DailyDate(pub Date);
+
+/// The part of the date being changed on the GBA date menu.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateField {
+    #[default]
+    Year,
+    Month,
+    Day,
+}
+
+impl DateField {
+    #[must_use]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    #[must_use]
+    pub fn cycle(self, forward: bool) -> DateField {
+        match (self, forward) {
+            (DateField::Year, true) | (DateField::Day, false) => DateField::Month,
+            (DateField::Month, true) | (DateField::Year, false) => DateField::Day,
+            (DateField::Day, true) | (DateField::Month, false) => DateField::Year,
+        }
+    }
+}
+
+/// Best daily challenge score for each day played.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DailyBests(pub(crate) BTreeMap<Date, u32>);
+
+impl DailyBests {
+    #[must_use]
+    pub fn best(&self, date: Date) -> u32 {
+        self.0.get(&date).copied().unwrap_or_default()
+    }
+
+    /// Records `score` for `date`, returning whether it beat the previous best.
+    pub fn submit(&mut self, date: Date, score: u32) -> bool {
+        let best = self.0.entry(date).or_default();
+        let beaten = score > *best;
+        *best = (*best).max(score);
+        beaten
+    }
+}
+
+#[cfg(feature = "gba")]
+pub fn date_menu(
+    actions: Res<ActionState>,
+    mut date: ResMut<DailyDate>,
+    mut field: ResMut<DateField>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::MoveRight) {
+        *field = field.cycle(true);
+    } else if actions.just_pressed(Action::MoveLeft) {
+        *field = field.cycle(false);
+    } else if actions.just_pressed(Action::MoveUp) {
+        date.0.step(*field, true);
+    } else if actions.just_pressed(Action::MoveDown) {
+        date.0.step(*field, false);
+    } else if actions.just_pressed(Action::Confirm) {
+        let Date { year, month, day } = date.0;
+        log::info!("Starting daily challenge for {year}-{month:02}-{day:02}");
+        next_state.set(GameState::Playing);
+    } else if actions.just_pressed(Action::Back) {
+        next_state.set(GameState::Title);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DailyBests, Date, DateField};
+    use crate::dropper::Dropper;
+
+    #[test]
+    pub fn unix_days_to_date() {
+        assert_eq!(Date::from_unix_days(0), Date::new(1970, 1, 1).unwrap());
+        assert_eq!(
+            Date::from_unix_days(11_016),
+            Date::new(2000, 2, 29).unwrap()
+        );
+        assert_eq!(
+            Date::from_unix_days(20_000),
+            Date::new(2024, 10, 4).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn same_day_same_fruit() {
+        let today = Date::new(2025, 3, 14).unwrap();
+        let tomorrow = Date::new(2025, 3, 15).unwrap();
+        assert_eq!(today.seed(), Date::new(2025, 3, 14).unwrap().seed());
+        assert_ne!(today.seed(), tomorrow.seed());
+
+        let queue = |date: Date| {
+            let mut dropper = Dropper::new(date.seed());
+            [(); 8].map(|()| {
+                let tier = dropper.current;
+                dropper.take(bevy::prelude::Entity::PLACEHOLDER);
+                tier
+            })
+        };
+        assert_eq!(queue(today), queue(today));
+    }
+
+    #[test]
+    pub fn stepping_keeps_date_valid() {
+        let mut date = Date::new(2024, 1, 31).unwrap();
+        date.step(DateField::Month, true);
+        assert_eq!(date, Date::new(2024, 2, 29).unwrap());
+        date.step(DateField::Year, true);
+        assert_eq!(date, Date::new(2025, 2, 28).unwrap());
+        date.step(DateField::Day, true);
+        assert_eq!(date, Date::new(2025, 2, 1).unwrap());
+        date.step(DateField::Month, false);
+        date.step(DateField::Month, false);
+        assert_eq!(date, Date::new(2025, 12, 1).unwrap());
+    }
+
+    #[test]
+    pub fn daily_bests_are_per_day() {
+        let day = |day| Date::new(2025, 3, day).unwrap();
+        let mut bests = DailyBests::default();
+        assert!(bests.submit(day(1), 50));
+        assert!(!bests.submit(day(1), 40));
+        assert!(bests.submit(day(2), 10));
+        assert_eq!(bests.best(day(1)), 50);
+        assert_eq!(bests.best(day(2)), 10);
+        assert_eq!(bests.best(day(3)), 0);
+    }
+}
@@ -0,0 +1,307 @@
+use alloc::borrow::Cow;
+use core::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    fruit::{Diameter, Fruit, Tier},
+    input::{CurrentInput, TickInput},
+    rules::GameRules,
+    wall::{LEFT_WALL, RIGHT_WALL},
+};
+
+/// Only the smallest few tiers ever come out of the dropper; the rest have to be merged.
+const DROPPABLE_TIERS: u32 = 5;
+
+const DEFAULT_SEED: u32 = 0x6d65_6c30;
+
+/// Fruit are dropped from the top of the arena. A new one only appears once the last one has
+/// fallen this far, so they can't stack up inside each other.
+pub const SPAWN_ZONE_DEPTH: f32 = 24.;
+
+/// How fast the dropper slides along the top of the arena, in pixels per second.
+const DROPPER_SPEED: f32 = 80.;
+
+/// Xorshift RNG behind the fruit queue. Tiny, `no_std`, and the same on every platform, so a seed
+/// always gives the same sequence of fruit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FruitRng(pub(crate) u32);
+
+impl FruitRng {
+    #[must_use]
+    pub fn new(seed: u32) -> Self {
+        // Xorshift gets stuck on zero.
+        Self(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    pub fn tier(&mut self) -> Tier {
+        #[allow(clippy::cast_possible_truncation)]
+        Tier((self.next_u32() % DROPPABLE_TIERS) as u8)
+    }
+}
+
+/// Where the dropper's fruit come from.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FruitSource {
+    Random(FruitRng),
+    /// A fixed sequence, for puzzles.
+    Script {
+        tiers: Cow<'static, [Tier]>,
+        pos: usize,
+    },
+}
+
+impl FruitSource {
+    fn pull(&mut self) -> Tier {
+        match self {
+            FruitSource::Random(rng) => rng.tier(),
+            // Past the end it doesn't matter what we hand out, as `drops_left` stops any drops.
+            FruitSource::Script { tiers, pos } => {
+                let tier = tiers.get(*pos).copied().unwrap_or_default();
+                *pos += 1;
+                tier
+            }
+        }
+    }
+}
+
+/// The fruit waiting to be dropped, what comes after it, and the hold slot.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Dropper {
+    /// Arena-space x of the centre of the fruit about to drop.
+    pub x: f32,
+    /// Cooldown has passed and the last drop is out of the way.
+    pub ready: bool,
+    pub(crate) since_drop: Duration,
+    pub(crate) last_drop: Option<Entity>,
+    pub current: Tier,
+    pub next: Tier,
+    pub held: Option<Tier>,
+    /// Holding is allowed once per drop.
+    pub hold_used: bool,
+    /// How many more fruit can be dropped, for puzzles. `None` for endless modes.
+    pub drops_left: Option<usize>,
+    pub(crate) source: FruitSource,
+}
+
+impl Default for Dropper {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Dropper {
+    #[must_use]
+    pub fn new(seed: u32) -> Self {
+        Self::with_source(FruitSource::Random(FruitRng::new(seed)), None)
+    }
+
+    /// A dropper that hands out exactly `tiers`, in order, then stops.
+    #[must_use]
+    pub fn scripted(tiers: Cow<'static, [Tier]>) -> Self {
+        let drops = tiers.len();
+        Self::with_source(FruitSource::Script { tiers, pos: 0 }, Some(drops))
+    }
+
+    fn with_source(mut source: FruitSource, drops_left: Option<usize>) -> Self {
+        Self {
+            x: 100.,
+            ready: true,
+            since_drop: Duration::ZERO,
+            last_drop: None,
+            current: source.pull(),
+            next: source.pull(),
+            held: None,
+            hold_used: false,
+            drops_left,
+            source,
+        }
+    }
+
+    /// Ready, and there's still something to drop.
+    #[must_use]
+    pub fn can_drop(&self) -> bool {
+        self.ready && self.drops_left != Some(0)
+    }
+
+    /// Keeps the whole of the current fruit inside the walls, even after swapping to a bigger one.
+    fn clamp_to_arena(&mut self) {
+        let radius = self.current.diameter() / 2.;
+        self.x = self.x.clamp(LEFT_WALL + radius, RIGHT_WALL - radius);
+    }
+
+    fn advance(&mut self) -> Tier {
+        core::mem::replace(&mut self.next, self.source.pull())
+    }
+
+    /// Slides the dropper left (`dir` -1) or right (1) for `delta`.
+    pub fn steer(&mut self, dir: i8, delta: Duration) {
+        self.x += f32::from(dir) * DROPPER_SPEED * delta.as_secs_f32();
+        self.clamp_to_arena();
+    }
+
+    /// Moves the dropper straight to `x`, as far as the walls allow.
+    pub fn aim(&mut self, x: f32) {
+        self.x = x;
+        self.clamp_to_arena();
+    }
+
+    /// Moves the dropper the way one tick's `input` says to.
+    pub fn follow(&mut self, input: TickInput, delta: Duration) {
+        match input.aim {
+            Some(x) => self.aim(f32::from(x)),
+            None => self.steer(input.dir, delta),
+        }
+    }
+
+    /// Works out whether the dropper is ready for another fruit, `delta` after the last check.
+    pub(crate) fn tick(
+        &mut self,
+        delta: Duration,
+        fruits: &Query<(&Transform, &Diameter), With<Fruit>>,
+        rules: &GameRules,
+    ) {
+        if self.ready {
+            return;
+        }
+        self.since_drop += delta;
+
+        if let Some(last) = self.last_drop {
+            // Gone entirely if it merged away.
+            let clear = fruits.get(last).map_or(true, |(ts, dm)| {
+                ts.translation.y - dm.0 / 2. >= SPAWN_ZONE_DEPTH
+            });
+            if clear || !rules.single_active_drop {
+                self.last_drop = None;
+            }
+        }
+
+        self.ready = self.last_drop.is_none() && self.since_drop >= rules.drop_cooldown;
+    }
+
+    /// Where the current fruit spawns: its centre, with its top at the top of the arena.
+    #[must_use]
+    pub fn spawn_pos(&self) -> Vec2 {
+        vec2(self.x, self.current.diameter() / 2.)
+    }
+
+    /// Hands out the current fruit to be dropped and brings the next one up from the queue.
+    /// The dropper stays busy until [`tick_dropper`] sees `dropped` clear the spawn zone.
+    pub fn take(&mut self, dropped: Entity) -> Tier {
+        self.ready = false;
+        self.since_drop = Duration::ZERO;
+        self.last_drop = Some(dropped);
+        self.hold_used = false;
+        if let Some(drops_left) = &mut self.drops_left {
+            *drops_left = drops_left.saturating_sub(1);
+        }
+        let next = self.advance();
+        let dropped = core::mem::replace(&mut self.current, next);
+        self.clamp_to_arena();
+        dropped
+    }
+
+    /// Swaps the current fruit into the hold slot, bringing out the held one or, if the slot
+    /// was empty, the next one from the queue. Does nothing if already used this drop.
+    pub fn hold(&mut self) {
+        if self.hold_used {
+            return;
+        }
+        self.hold_used = true;
+        self.current = match self.held.replace(self.current) {
+            Some(held) => held,
+            None => self.advance(),
+        };
+        self.clamp_to_arena();
+    }
+}
+
+pub fn tick_dropper(
+    mut dropper: ResMut<Dropper>,
+    fruits: Query<(&Transform, &Diameter), With<Fruit>>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+) {
+    dropper.tick(time.delta(), &fruits, &rules);
+}
+
+pub(crate) fn hold_enabled(rules: Res<GameRules>) -> bool {
+    rules.hold
+}
+
+/// Slides the dropper along the top of the arena.
+pub fn move_dropper(input: Res<CurrentInput>, mut dropper: ResMut<Dropper>, time: Res<Time>) {
+    if let Some(input) = input.0 {
+        dropper.follow(input, time.delta());
+    }
+}
+
+pub fn hold_fruit(input: Res<CurrentInput>, mut dropper: ResMut<Dropper>) {
+    if input.0.is_some_and(|input| input.hold) {
+        dropper.hold();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use assert_float_eq::assert_float_relative_eq;
+    use bevy::prelude::Entity;
+
+    use super::Dropper;
+    use crate::{
+        input::TickInput,
+        wall::{LEFT_WALL, RIGHT_WALL},
+    };
+
+    #[test]
+    pub fn hold_once_per_drop() {
+        let mut dropper = Dropper::new(1234);
+        let (first, second) = (dropper.current, dropper.next);
+
+        dropper.hold();
+        assert_eq!(dropper.held, Some(first));
+        assert_eq!(dropper.current, second);
+
+        // Second press before dropping does nothing.
+        dropper.hold();
+        assert_eq!(dropper.held, Some(first));
+        assert_eq!(dropper.current, second);
+
+        assert_eq!(dropper.take(Entity::PLACEHOLDER), second);
+        let third = dropper.current;
+        dropper.hold();
+        assert_eq!(dropper.current, first);
+        assert_eq!(dropper.held, Some(third));
+    }
+
+    #[test]
+    pub fn aiming_stays_inside_walls() {
+        let mut dropper = Dropper::new(1234);
+        let radius = dropper.current.diameter() / 2.;
+        let aim = |x| TickInput {
+            dir: 1,
+            aim: Some(x),
+            ..TickInput::default()
+        };
+
+        // Aiming wins over steering.
+        dropper.follow(aim(120), Duration::from_secs(1));
+        assert_float_relative_eq!(dropper.x, 120.);
+
+        dropper.follow(aim(i16::MIN), Duration::ZERO);
+        assert_float_relative_eq!(dropper.x, LEFT_WALL + radius);
+        dropper.follow(aim(i16::MAX), Duration::ZERO);
+        assert_float_relative_eq!(dropper.x, RIGHT_WALL - radius);
+    }
+}
@@ -1,10 +1,22 @@
+#[cfg(feature = "desktop")]
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+#[cfg(feature = "desktop")]
+use core::time::Duration;
+
 use bevy::prelude::*;
 
-#[cfg(feature = "gba")]
-use crate::Sprites;
+#[cfg(feature = "desktop")]
+use crate::{ArenaCoords, replay::Unreplayable};
 use crate::{
     Gravity, Root, Velocity,
+    dropper::Dropper,
+    ghost::Ghost,
+    input::CurrentInput,
+    level::ActiveLevel,
+    overflow::OverLine,
     physics::{ActingForces, Physics},
+    rules::{GameRules, PhysicsPreset},
 };
 
 #[derive(Component, Clone, Copy, Default, Debug)]
@@ -16,39 +28,137 @@ pub struct Collided(pub u32);
 #[derive(Component, Default, Debug)]
 pub struct Fruit;
 
+/// Diameters of each fruit tier, smallest (cherry) to largest (watermelon).
+pub const TIER_DIAMETERS: [f32; 11] = [10., 12., 14., 16., 20., 24., 28., 32., 38., 44., 52.];
+
+pub static TIER_NAMES: [&str; 11] = [
+    "cherry",
+    "strawberry",
+    "grape",
+    "dekopon",
+    "persimmon",
+    "apple",
+    "pear",
+    "peach",
+    "pineapple",
+    "melon",
+    "watermelon",
+];
+
+/// How far up the fruit chain a fruit is. Two touching fruit of the same tier merge into the next.
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tier(pub u8);
+
+impl Tier {
+    /// The watermelon. Two of these merging clear each other instead of growing.
+    #[allow(clippy::cast_possible_truncation)]
+    pub const MAX: Tier = Tier(TIER_DIAMETERS.len() as u8 - 1);
+
+    #[must_use]
+    pub fn diameter(self) -> f32 {
+        TIER_DIAMETERS[usize::from(self.0.min(Self::MAX.0))]
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        TIER_NAMES[usize::from(self.0.min(Self::MAX.0))]
+    }
+
+    /// The tier two of these merge into, or `None` for the top tier.
+    #[must_use]
+    pub fn next(self) -> Option<Tier> {
+        (self < Self::MAX).then_some(Tier(self.0 + 1))
+    }
+}
+
 #[derive(Bundle, Default, Debug)]
 pub struct FruitBundle {
-    marker: Fruit,
-    transform: Transform,
-    velocity: Velocity,
-    acting_forces: ActingForces,
-    grav_marker: Gravity,
-    diameter: Diameter,
-    physics: Physics,
+    pub(crate) marker: Fruit,
+    pub(crate) tier: Tier,
+    pub(crate) transform: Transform,
+    pub(crate) velocity: Velocity,
+    pub(crate) acting_forces: ActingForces,
+    pub(crate) grav_marker: Gravity,
+    pub(crate) diameter: Diameter,
+    pub(crate) physics: Physics,
+    pub(crate) over_line: OverLine,
 }
 
-// static FRUIT_POS: [Vec2; 2] = [vec2(90., 999.), vec2(90., 30.)];
-static FRUIT_POS: [Vec2; 0] = [];
+impl FruitBundle {
+    /// A fruit just let go of by the dropper, centred on `pos`.
+    pub(crate) fn dropped(tier: Tier, pos: Vec2, physics: PhysicsPreset) -> Self {
+        Self {
+            tier,
+            diameter: Diameter(tier.diameter()),
+            transform: Transform::from_translation(pos.extend(1.0))
+                .with_scale(Vec2::splat(tier.diameter()).extend(1.)),
+            velocity: Velocity(physics.drop_velocity()),
+            ..default()
+        }
+    }
+}
 
-pub static FRUIT_DIAMETER: f32 = 16.;
-#[cfg(feature = "gba")]
+/// How a frontend draws fruit. Gameplay only ever spawns plain [`FruitBundle`]s; with a
+/// [`FruitVisualsPlugin`] for the frontend's visuals, each one is then dressed for its tier.
+/// Without one, as in a headless build, fruit just go undrawn.
+pub trait FruitVisuals: Resource {
+    /// Gives `fruit` the look of `tier`. Called for each new fruit, and again whenever a merge
+    /// moves it up a tier.
+    fn apply(&self, tier: Tier, fruit: &mut EntityCommands);
+}
+
+/// Dresses every fruit with `V`, once the frontend has put `V` in the world. The ghost's fruit
+/// are left to the ghost's own visuals.
+pub struct FruitVisualsPlugin<V>(PhantomData<V>);
+
+impl<V> Default for FruitVisualsPlugin<V> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<V: FruitVisuals> Plugin for FruitVisualsPlugin<V> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            add_fruit_visuals::<V>.run_if(resource_exists::<V>),
+        );
+    }
+}
+
+fn add_fruit_visuals<V: FruitVisuals>(
+    mut commands: Commands,
+    visuals: Res<V>,
+    fruits: Query<(Entity, &Tier), (Changed<Tier>, With<Fruit>, Without<Ghost>)>,
+) {
+    for (entity, &tier) in &fruits {
+        visuals.apply(tier, &mut commands.entity(entity));
+    }
+}
+
+/// Starting layout for the puzzle being played, if any.
 pub fn add_fruit(
     mut commands: Commands,
-    sprites: NonSend<Option<Sprites>>,
     root: Single<Entity, With<Root>>,
+    level: Option<Res<ActiveLevel>>,
 ) {
-    let sprites = sprites.as_ref().unwrap();
+    let Some(level) = level else {
+        return;
+    };
 
-    for fruit in FRUIT_POS {
+    for fruit in level.level.fruit.iter() {
+        let diameter = fruit.tier.diameter();
         let entity = commands
             .spawn((
                 FruitBundle {
-                    transform: Transform::from_translation(fruit.extend(1.0))
-                        .with_scale(Vec2::splat(FRUIT_DIAMETER).extend(1.)),
-                    diameter: Diameter(FRUIT_DIAMETER),
+                    tier: fruit.tier,
+                    diameter: Diameter(diameter),
+                    transform: Transform::from_translation(fruit.pos.extend(1.0))
+                        .with_scale(Vec2::splat(diameter).extend(1.)),
+                    velocity: Velocity(fruit.vel),
                     ..default()
                 },
-                sprites.player.clone(),
                 Collided(0),
             ))
             .id();
@@ -56,132 +166,230 @@ pub fn add_fruit(
         commands.entity(*root).add_child(entity);
     }
 }
-#[cfg(feature = "desktop")]
-pub fn add_fruit(
+
+pub fn place_fruit(
+    input: Res<CurrentInput>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
     root: Single<Entity, With<Root>>,
+    mut dropper: ResMut<Dropper>,
+    rules: Res<GameRules>,
 ) {
-    for fruit in FRUIT_POS {
+    if dropper.can_drop() && input.0.is_some_and(|input| input.drop) {
         let entity = commands
             .spawn((
-                FruitBundle {
-                    diameter: Diameter(FRUIT_DIAMETER),
-                    transform: Transform::from_translation(fruit.extend(1.0))
-                        .with_scale(Vec2::splat(FRUIT_DIAMETER).extend(1.)),
-                    ..default()
-                },
-                Mesh2d(meshes.add(Circle::default())),
-                MeshMaterial2d(materials.add(Color::linear_rgb(1.0, 0.0, 1.0))),
+                FruitBundle::dropped(dropper.current, dropper.spawn_pos(), rules.physics),
                 Collided(0),
             ))
             .id();
-
         commands.entity(*root).add_child(entity);
+        dropper.take(entity);
     }
 }
 
-#[cfg(feature = "gba")]
-pub fn place_fruit(
-    gamepad: Single<&Gamepad>,
-    mut commands: Commands,
-    sprites: NonSend<Option<Sprites>>,
-    root: Single<Entity, With<Root>>,
-) {
-    if gamepad.just_pressed(GamepadButton::East) {
-        let sprites = sprites.as_ref().unwrap();
+/// Lets fruit be picked up and thrown about with the pointer, for trying things out. Off in
+/// normal play. A run the sandbox has moved fruit in is [`Unreplayable`].
+#[cfg(feature = "desktop")]
+#[derive(Resource, Debug, Default)]
+pub struct Sandbox(pub bool);
 
-        let fruit = vec2(100., 0.);
-        use crate::MOON_PHYSICS;
+#[cfg(feature = "desktop")]
+pub(crate) struct SandboxPlugin;
 
-        let entity = commands
-            .spawn((
-                FruitBundle {
-                    transform: Transform::from_xyz(fruit.x, fruit.y, 0.0),
-                    diameter: Diameter(FRUIT_DIAMETER),
-                    velocity: Velocity(vec2(
-                        if MOON_PHYSICS { 0.7 } else { 0.0 },
-                        if MOON_PHYSICS { 10.0 } else { 0.0 },
-                    )),
-                    ..default()
-                },
-                sprites.player.clone(),
-                Collided(0),
-            ))
-            .id();
+#[cfg(feature = "desktop")]
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sandbox>()
+            .add_observer(on_drag_start_fruit)
+            .add_observer(on_drag_move_fruit)
+            .add_observer(on_drag_end_fruit)
+            .add_observer(on_click_delete_fruit);
+    }
+}
 
-        commands.entity(*root).add_child(entity);
-        return;
+/// How far back the pointer's motion counts towards a throw.
+#[cfg(feature = "desktop")]
+const FLING_WINDOW: Duration = Duration::from_millis(100);
+
+/// A fruit being dragged in the [`Sandbox`]. Physics leaves it alone until it's let go.
+#[cfg(feature = "desktop")]
+#[derive(Component, Debug)]
+pub struct Held {
+    /// From the pointer to the fruit's centre, in the arena.
+    grab: Vec2,
+    /// Where the pointer has been in the arena lately, and when.
+    trail: Vec<(Duration, Vec2)>,
+}
+
+#[cfg(feature = "desktop")]
+impl Held {
+    fn follow(&mut self, now: Duration, pointer: Vec2) -> Vec2 {
+        self.trail
+            .retain(|&(seen, _)| now.saturating_sub(seen) <= FLING_WINDOW);
+        self.trail.push((now, pointer));
+        pointer + self.grab
+    }
+
+    /// How fast the pointer was moving when let go at `now`.
+    fn fling(&self, now: Duration) -> Vec2 {
+        let mut recent = self
+            .trail
+            .iter()
+            .filter(|&&(seen, _)| now.saturating_sub(seen) <= FLING_WINDOW);
+        let (Some(&(start, from)), Some(&(end, to))) = (recent.next(), recent.next_back()) else {
+            return Vec2::ZERO;
+        };
+        // Several moves in one frame all get the same time.
+        if end == start {
+            return Vec2::ZERO;
+        }
+        (to - from) / (end - start).as_secs_f32()
     }
 }
 
 #[cfg(feature = "desktop")]
-pub fn place_fruit(
-    // gamepad: Single<&Gamepad>,
-    gamepad: Option<Single<(&Gamepad)>>,
-    keys: Res<ButtonInput<KeyCode>>,
+fn on_drag_start_fruit(
+    drag: Trigger<Pointer<DragStart>>,
+    sandbox: Res<Sandbox>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    space: ArenaCoords,
+    transforms: Query<&Transform, (With<Fruit>, Without<Ghost>)>,
+    time: Res<Time<Real>>,
+    mut unreplayable: ResMut<Unreplayable>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    root: Single<Entity, With<Root>>,
 ) {
-    // let (root, _) = *root;
-    // info!("{n:?}");
-
-    use crate::MOON_PHYSICS;
-    if gamepad.is_some_and(|g| g.just_pressed(GamepadButton::East))
-        || keys.just_pressed(KeyCode::Space)
-    {
-        let fruit = vec2(100., 0.);
-
-        let entity = commands
-            .spawn((
-                FruitBundle {
-                    diameter: Diameter(FRUIT_DIAMETER),
-                    transform: Transform::from_translation(fruit.extend(1.0))
-                        .with_scale(Vec2::splat(FRUIT_DIAMETER).extend(1.)),
-                    velocity: Velocity(vec2(
-                        if MOON_PHYSICS { 0.7 } else { 0.0 },
-                        if MOON_PHYSICS { 10.0 } else { 0.0 },
-                    )),
-                    ..default()
-                },
-                Mesh2d(meshes.add(Circle::default())),
-                MeshMaterial2d(materials.add(Color::linear_rgb(1.0, 0.0, 1.0))),
-                Collided(0),
-            ))
-            .observe(on_click_delete_fruit)
-            .id();
-        commands.entity(*root).add_child(entity);
+    if !sandbox.0 {
         return;
     }
-
-    // if gamepad.just_pressed(GamepadButton::East) {
-    //     add_fruit(commands, meshes, materials);
-    // }
+    let Ok(transform) = transforms.get(drag.target()) else {
+        return;
+    };
+    unreplayable.0 = true;
+    let Some(pointer) = space.viewport_to_arena(*camera, drag.pointer_location.position) else {
+        return;
+    };
+    commands
+        .entity(drag.target())
+        .remove::<(Physics, Gravity)>()
+        .insert((
+            Held {
+                grab: transform.translation.truncate() - pointer,
+                trail: vec![(time.elapsed(), pointer)],
+            },
+            Velocity::default(),
+            ActingForces::default(),
+        ));
 }
 
 #[cfg(feature = "desktop")]
-pub fn on_drag_move_fruit(
+fn on_drag_move_fruit(
     drag: Trigger<Pointer<Drag>>,
-    mut transforms: Query<&mut Transform, With<Fruit>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    space: ArenaCoords,
+    mut held: Query<(&mut Transform, &mut Held), With<Fruit>>,
+    time: Res<Time<Real>>,
 ) {
-    if let Ok(mut transform) = transforms.get_mut(drag.target()) {
-        transform.translation.x += (drag.delta.x);
-        transform.translation.y += (drag.delta.y);
+    let Ok((mut transform, mut held)) = held.get_mut(drag.target()) else {
+        return;
+    };
+    if let Some(pointer) = space.viewport_to_arena(*camera, drag.pointer_location.position) {
+        let pos = held.follow(time.elapsed(), pointer);
+        transform.translation = pos.extend(transform.translation.z);
     }
 }
 
+/// Lets go of a [`Held`] fruit, throwing it the way the pointer was going.
 #[cfg(feature = "desktop")]
-pub fn on_click_delete_fruit(
+fn on_drag_end_fruit(
+    drag: Trigger<Pointer<DragEnd>>,
+    held: Query<&Held, With<Fruit>>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+) {
+    let Ok(held) = held.get(drag.target()) else {
+        return;
+    };
+    commands.entity(drag.target()).remove::<Held>().insert((
+        Physics,
+        Gravity,
+        Velocity(held.fling(time.elapsed())),
+    ));
+}
+
+/// Clicking a fruit in the [`Sandbox`] gets rid of it, unless it was just being dragged.
+#[cfg(feature = "desktop")]
+fn on_click_delete_fruit(
     click: Trigger<Pointer<Click>>,
-    mut entities: Query<Entity>,
+    sandbox: Res<Sandbox>,
+    fruit: Query<Has<Held>, (With<Fruit>, Without<Ghost>)>,
+    mut unreplayable: ResMut<Unreplayable>,
     mut commands: Commands,
-) -> Result<()> {
-    let entity = entities.get(click.target())?;
+) {
+    // Clicks come in before the drag ends.
+    if sandbox.0 && fruit.get(click.target()) == Ok(false) {
+        commands.entity(click.target()).despawn();
+        unreplayable.0 = true;
+    }
+}
 
-    commands.entity(entity).despawn();
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "desktop")]
+    use core::time::Duration;
 
-    Ok(())
+    use bevy::prelude::*;
+
+    #[cfg(feature = "desktop")]
+    use super::Held;
+    use super::{Fruit, FruitVisuals, FruitVisualsPlugin, Tier};
+    use crate::ghost::Ghost;
+
+    /// Stands in for a sprite or mesh.
+    #[derive(Component, Debug, PartialEq)]
+    struct Look(Tier);
+
+    #[derive(Resource)]
+    struct Looks;
+
+    impl FruitVisuals for Looks {
+        fn apply(&self, tier: Tier, fruit: &mut EntityCommands) {
+            fruit.insert(Look(tier));
+        }
+    }
+
+    #[test]
+    pub fn visuals_follow_the_tier() {
+        let mut app = App::new();
+        app.add_plugins(FruitVisualsPlugin::<Looks>::default())
+            .insert_resource(Looks);
+        let fruit = app.world_mut().spawn((Fruit, Tier(2))).id();
+        let ghost = app.world_mut().spawn((Fruit, Tier(2), Ghost)).id();
+
+        app.update();
+        assert_eq!(app.world().get::<Look>(fruit), Some(&Look(Tier(2))));
+        assert_eq!(app.world().get::<Look>(ghost), None);
+
+        // Merged up a tier.
+        *app.world_mut().get_mut::<Tier>(fruit).unwrap() = Tier(3);
+        app.update();
+        assert_eq!(app.world().get::<Look>(fruit), Some(&Look(Tier(3))));
+    }
+
+    #[cfg(feature = "desktop")]
+    #[test]
+    pub fn flings_follow_the_last_moves() {
+        let ms = Duration::from_millis;
+        let mut held = Held {
+            grab: vec2(-4., -4.),
+            trail: vec![(ms(0), vec2(0., 0.))],
+        };
+
+        // A slow start, then a quick flick right before letting go.
+        assert_eq!(held.follow(ms(180), vec2(10., 10.)), vec2(6., 6.));
+        held.follow(ms(250), vec2(20., 10.));
+        held.follow(ms(300), vec2(40., 10.));
+        assert_eq!(held.fling(ms(300)), vec2(400., 0.));
+
+        // Holding still before letting go throws nothing.
+        assert_eq!(held.fling(ms(500)), Vec2::ZERO);
+    }
 }
@@ -1,3 +1,6 @@
+use alloc::{vec, vec::Vec};
+use core::time::Duration;
+
 use agb::{
     display::{
         Priority,
@@ -5,11 +8,24 @@ use agb::{
         tiled::{RegularBackgroundSize, TiledMap},
     },
     include_background_gfx,
+    save::{Error as SaveError, SaveData},
 };
 use bevy::prelude::*;
-use bevy_mod_gba::{Sprite, SpriteHandles, Video};
+use bevy_mod_gba::{SaveManager, Sprite, SpriteHandles, Video};
 
-use crate::Sprites;
+use crate::{
+    Mel0nSetupSet, Root, Sprites,
+    bindings::ButtonBindings,
+    countdown::Countdown,
+    daily::{DailyDate, Date, DateField},
+    dropper::Dropper,
+    fruit::{FruitVisuals, FruitVisualsPlugin, Tier},
+    input::{Action, action_just_pressed},
+    mode::{GameMode, GameState},
+    rules::GameRules,
+    save::{SaveBackend, SaveMedia, SavePlugin},
+    snapshot::{LoadSnapshot, Snapshot},
+};
 
 include_background_gfx!(generated_background, "000000", DATA => "assets/test_logo_basic.png");
 
@@ -20,13 +36,95 @@ pub struct Mel0nGbaPlugin;
 
 impl Plugin for Mel0nGbaPlugin {
     fn build(&self, app: &mut App) {
-        app.init_non_send_resource::<Option<Sprites>>().add_systems(
+        app.add_plugins((
+            SavePlugin::<CartSave>::from_world(),
+            FruitVisualsPlugin::<FruitSprites>::default(),
+        ))
+        .init_non_send_resource::<Option<Sprites>>()
+        .add_systems(
             Startup,
-            (setup_video, load_sprites).chain().in_set(Mel0nGbaSetupSet),
+            (
+                (setup_video, load_sprites).chain().in_set(Mel0nGbaSetupSet),
+                spawn_hud.after(Mel0nSetupSet),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                update_hud,
+                update_title_cursor,
+                update_date_menu,
+                update_controls_menu,
+                update_countdown,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                quicksave.run_if(
+                    in_state(GameState::Playing).and(action_just_pressed(Action::QuickSave)),
+                ),
+                quickload
+                    .run_if(in_state(GameState::Title).and(action_just_pressed(Action::QuickSave))),
+            ),
         );
     }
 }
 
+/// Screen position of the hold slot, to the right of the arena.
+const HOLD_SLOT_POS: Vec2 = Vec2::new(200., 24.);
+/// The countdown is a column of pips down the right of the screen, one per chunk of time left.
+const COUNTDOWN_POS: Vec2 = Vec2::new(216., 8.);
+const COUNTDOWN_PIPS: usize = 8;
+const COUNTDOWN_PIP_TIME: Duration = Duration::from_secs(15);
+const COUNTDOWN_PIP_SPACING: f32 = 18.;
+
+/// Screen position of the first title menu entry. The rest follow below it.
+const TITLE_MENU_POS: Vec2 = Vec2::new(40., 56.);
+const TITLE_MENU_SPACING: f32 = 16.;
+/// Screen position of the year on the date menu, with the month and day to its right. The cursor
+/// sits under the part being changed.
+const DATE_MENU_POS: Vec2 = Vec2::new(72., 72.);
+const DATE_MENU_SPACING: f32 = 40.;
+const DATE_CURSOR_OFFSET: Vec2 = Vec2::new(0., 12.);
+/// How many digits the year, month and day each get.
+const DATE_DIGITS: [u32; 3] = [4, 2, 2];
+const DIGIT_WIDTH: f32 = 8.;
+/// Screen position of the number of the preset layout on the controls menu.
+const CONTROLS_MENU_POS: Vec2 = Vec2::new(116., 76.);
+/// Where HUD sprites go when there's nothing to show.
+const OFF_SCREEN: Vec2 = Vec2::new(240., 160.);
+
+#[derive(Component)]
+struct HoldSlot;
+
+/// The fruit hanging at the top of the arena, only shown once it can be dropped.
+#[derive(Component)]
+struct DropperPreview;
+
+/// One pip of the time attack countdown.
+#[derive(Component)]
+struct CountdownPip(usize);
+
+/// Points at the selected mode on the title screen.
+#[derive(Component)]
+struct TitleCursor;
+
+/// Points at the part of the date being changed on the date menu.
+#[derive(Component)]
+struct DateCursor;
+
+/// One digit of the date on the date menu: which part of the date, and how far from its left.
+#[derive(Component)]
+struct DateDigit {
+    field: usize,
+    place: u32,
+}
+
+/// The number of the preset layout picked on the controls menu, counting from 1.
+#[derive(Component)]
+struct ControlsPreset;
+
 fn setup_video(mut video: ResMut<Video>) {
     let (background, mut vram) = video.tiled0();
 
@@ -42,23 +140,354 @@ fn setup_video(mut video: ResMut<Video>) {
     map.set_visible(true);
 }
 
+/// There are fewer frames of fruit art than tiers, so the biggest tiers share the last one.
+fn fruit_sprite(fruits: &[Sprite], tier: Tier) -> &Sprite {
+    &fruits[usize::from(tier.0).min(fruits.len() - 1)]
+}
+
+#[derive(Resource)]
+struct FruitSprites(Vec<Sprite>);
+
+impl FruitVisuals for FruitSprites {
+    /// Sprites hang down and right from their position, so the sprite sits on a child moved back
+    /// by half the fruit. The fruit's scale is its diameter, so that's half a unit.
+    fn apply(&self, tier: Tier, fruit: &mut EntityCommands) {
+        let sprite = fruit_sprite(&self.0, tier).clone();
+        fruit
+            .despawn_related::<Children>()
+            .with_child((sprite, Transform::from_xyz(-0.5, -0.5, 0.)));
+    }
+}
+
 fn load_sprites(
+    mut commands: Commands,
     mut loader: NonSendMut<SpriteLoader>,
     mut handles: NonSendMut<SpriteHandles>,
     mut sprites: NonSendMut<Option<Sprites>>,
 ) -> Result<()> {
     static GRAPHICS: &agb::display::object::Graphics =
         agb::include_aseprite!("./assets/fruits.aseprite");
+    static DIGITS: &agb::display::object::Graphics =
+        agb::include_aseprite!("./assets/digits.aseprite");
 
-    let hero: &agb::display::object::Sprite = GRAPHICS.sprites().get(3).ok_or("Damn")?;
+    let fruits: Vec<Sprite> = GRAPHICS
+        .sprites()
+        .iter()
+        .map(|sprite| Sprite::new(handles.add(loader.get_vram_sprite(sprite))))
+        .collect();
+    let player = fruits.get(3).ok_or("Damn")?.clone();
+    let digits = DIGITS
+        .sprites()
+        .iter()
+        .map(|sprite| Sprite::new(handles.add(loader.get_vram_sprite(sprite))))
+        .collect();
 
-    let vram = loader.get_vram_sprite(hero);
+    commands.insert_resource(FruitSprites(fruits.clone()));
+    *sprites = Some(Sprites {
+        player,
+        fruits,
+        digits,
+    });
 
-    let handle = handles.add(vram);
+    Ok(())
+}
 
-    let player = Sprite::new(handle);
+fn spawn_hud(
+    mut commands: Commands,
+    sprites: NonSend<Option<Sprites>>,
+    root: Single<Entity, With<Root>>,
+) {
+    let Some(sprites) = sprites.as_ref() else {
+        return;
+    };
 
-    *sprites = Some(Sprites { player });
+    let preview = commands
+        .spawn((DropperPreview, sprites.player.clone(), Transform::default()))
+        .id();
+    commands.entity(*root).add_child(preview);
 
-    Ok(())
+    commands.spawn((
+        TitleCursor,
+        sprites.player.clone(),
+        Transform::from_translation(TITLE_MENU_POS.extend(0.)),
+    ));
+
+    commands.spawn((
+        DateCursor,
+        sprites.player.clone(),
+        Transform::from_translation(OFF_SCREEN.extend(0.)),
+    ));
+
+    for (field, &len) in DATE_DIGITS.iter().enumerate() {
+        for place in 0..len {
+            commands.spawn((
+                DateDigit { field, place },
+                sprites.digits[0].clone(),
+                Transform::from_translation(OFF_SCREEN.extend(0.)),
+            ));
+        }
+    }
+
+    commands.spawn((
+        ControlsPreset,
+        sprites.digits[1].clone(),
+        Transform::from_translation(OFF_SCREEN.extend(0.)),
+    ));
+
+    for pip in 0..COUNTDOWN_PIPS {
+        commands.spawn((
+            CountdownPip(pip),
+            sprites.player.clone(),
+            Transform::from_translation(OFF_SCREEN.extend(0.)),
+        ));
+    }
+
+    // The rules aren't known until a run starts, so it's there in every mode but only shown in
+    // those with hold.
+    commands.spawn((
+        HoldSlot,
+        sprites.player.clone(),
+        Transform::from_translation(OFF_SCREEN.extend(0.)),
+    ));
+}
+
+/// Shows the fruit in the hold slot and the dropper, each with its tier's sprite.
+fn update_hud(
+    mut hold_slot: Query<(&mut Transform, &mut Sprite), (With<HoldSlot>, Without<DropperPreview>)>,
+    mut preview: Single<(&mut Transform, &mut Sprite), With<DropperPreview>>,
+    sprites: NonSend<Option<Sprites>>,
+    dropper: Res<Dropper>,
+    rules: Res<GameRules>,
+    state: Res<State<GameState>>,
+) {
+    if !(dropper.is_changed() || rules.is_changed() || state.is_changed()) {
+        return;
+    }
+    let Some(sprites) = sprites.as_ref() else {
+        return;
+    };
+
+    for (mut transform, mut sprite) in &mut hold_slot {
+        let pos = match dropper.held {
+            Some(tier) if rules.hold => {
+                *sprite = fruit_sprite(&sprites.fruits, tier).clone();
+                HOLD_SLOT_POS
+            }
+            _ => OFF_SCREEN,
+        };
+        transform.translation = pos.extend(0.);
+    }
+
+    let (transform, sprite) = &mut *preview;
+    **sprite = fruit_sprite(&sprites.fruits, dropper.current).clone();
+
+    // Sprites are placed by their top-left corner.
+    let pos = if dropper.can_drop() && *state == GameState::Playing {
+        dropper.spawn_pos() - Vec2::splat(dropper.current.diameter() / 2.)
+    } else {
+        OFF_SCREEN
+    };
+    transform.translation = pos.extend(0.);
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn update_title_cursor(
+    mut cursor: Single<&mut Transform, With<TitleCursor>>,
+    mode: Res<GameMode>,
+    state: Res<State<GameState>>,
+) {
+    let pos = if *state == GameState::Title {
+        TITLE_MENU_POS + Vec2::Y * TITLE_MENU_SPACING * mode.index() as f32
+    } else {
+        OFF_SCREEN
+    };
+    cursor.translation = pos.extend(0.);
+}
+
+/// Spells out the date a digit to a sprite, with the cursor under the part being changed.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn update_date_menu(
+    mut cursor: Single<&mut Transform, With<DateCursor>>,
+    mut digits: Query<(&mut Transform, &mut Sprite, &DateDigit), Without<DateCursor>>,
+    sprites: NonSend<Option<Sprites>>,
+    date: Res<DailyDate>,
+    field: Res<DateField>,
+    state: Res<State<GameState>>,
+) {
+    if !(date.is_changed() || field.is_changed() || state.is_changed()) {
+        return;
+    }
+    let Some(sprites) = sprites.as_ref() else {
+        return;
+    };
+    let picking = *state == GameState::PickDate;
+
+    let Date { year, month, day } = date.0;
+    let values = [u32::from(year), u32::from(month), u32::from(day)];
+    for (mut transform, mut sprite, digit) in &mut digits {
+        let pos = if picking {
+            let power = DATE_DIGITS[digit.field] - 1 - digit.place;
+            let value = values[digit.field] / 10u32.pow(power) % 10;
+            *sprite = sprites.digits[value as usize].clone();
+            DATE_MENU_POS
+                + Vec2::X
+                    * (DATE_MENU_SPACING * digit.field as f32 + DIGIT_WIDTH * digit.place as f32)
+        } else {
+            OFF_SCREEN
+        };
+        transform.translation = pos.extend(0.);
+    }
+
+    let pos = if picking {
+        DATE_MENU_POS + DATE_CURSOR_OFFSET + Vec2::X * DATE_MENU_SPACING * field.index() as f32
+    } else {
+        OFF_SCREEN
+    };
+    cursor.translation = pos.extend(0.);
+}
+
+/// Shows which of the preset layouts is picked on the controls menu.
+fn update_controls_menu(
+    mut shown: Single<(&mut Transform, &mut Sprite), With<ControlsPreset>>,
+    sprites: NonSend<Option<Sprites>>,
+    buttons: Res<ButtonBindings>,
+    state: Res<State<GameState>>,
+) {
+    if !(buttons.is_changed() || state.is_changed()) {
+        return;
+    }
+    let Some(sprites) = sprites.as_ref() else {
+        return;
+    };
+
+    let (transform, sprite) = &mut *shown;
+    let pos = if *state == GameState::Controls {
+        let number = buttons.preset().unwrap_or(0) + 1;
+        **sprite = sprites.digits[number % 10].clone();
+        CONTROLS_MENU_POS
+    } else {
+        OFF_SCREEN
+    };
+    transform.translation = pos.extend(0.);
+}
+
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn update_countdown(
+    mut pips: Query<(&mut Transform, &CountdownPip)>,
+    countdown: Res<Countdown>,
+    state: Res<State<GameState>>,
+) {
+    if !(countdown.is_changed() || state.is_changed()) {
+        return;
+    }
+
+    let shown = match countdown.0 {
+        Some(remaining) if *state == GameState::Playing => remaining
+            .as_millis()
+            .div_ceil(COUNTDOWN_PIP_TIME.as_millis())
+            as usize,
+        _ => 0,
+    };
+    for (mut transform, CountdownPip(pip)) in &mut pips {
+        let pos = if *pip < shown {
+            COUNTDOWN_POS + Vec2::Y * COUNTDOWN_PIP_SPACING * *pip as f32
+        } else {
+            OFF_SCREEN
+        };
+        transform.translation = pos.extend(0.);
+    }
+}
+
+/// The cartridge's save chip, of whichever kind [`AgbSavePlugin`](bevy_mod_gba::AgbSavePlugin)
+/// was told to expect. Flash is erased a whole sector at a time, so a write puts back the rest of
+/// each sector it touches.
+pub struct CartSave {
+    /// `None` if agb had no save media to hand over.
+    manager: Option<SaveManager>,
+    len: usize,
+}
+
+impl FromWorld for CartSave {
+    fn from_world(world: &mut World) -> Self {
+        let mut manager = world.remove_resource::<SaveManager>();
+        let len = manager
+            .as_mut()
+            .and_then(|manager| manager.access().ok())
+            .map_or(0, |save| save.len());
+        Self { manager, len }
+    }
+}
+
+impl CartSave {
+    fn access(&mut self) -> Result<SaveData, CartSaveError> {
+        let manager = self
+            .manager
+            .as_mut()
+            .ok_or(CartSaveError(SaveError::NoMedia))?;
+        Ok(manager.access()?)
+    }
+}
+
+impl SaveBackend for CartSave {
+    type Error = CartSaveError;
+
+    fn capacity(&self) -> usize {
+        self.len
+    }
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), CartSaveError> {
+        Ok(self.access()?.read(offset, buf)?)
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), CartSaveError> {
+        let mut save = self.access()?;
+        let sectors = save.align_range(offset..offset + data.len());
+        let mut bytes = vec![0; sectors.len()];
+        save.read(sectors.start, &mut bytes)?;
+        bytes[offset - sectors.start..][..data.len()].copy_from_slice(data);
+        Ok(save
+            .prepare_write(sectors.clone())?
+            .write(sectors.start, &bytes)?)
+    }
+}
+
+#[derive(Debug)]
+pub struct CartSaveError(SaveError);
+
+impl From<SaveError> for CartSaveError {
+    fn from(err: SaveError) -> Self {
+        Self(err)
+    }
+}
+
+impl core::fmt::Display for CartSaveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            SaveError::NoMedia => write!(f, "no save chip on the cartridge"),
+            SaveError::OutOfBounds => write!(f, "past the end of the save chip"),
+            ref err => write!(f, "save chip error: {err:?}"),
+        }
+    }
+}
+
+/// The quicksave button saves the run being played over the one quicksave.
+fn quicksave(world: &mut World) {
+    let snapshot = Snapshot::capture(world);
+    match world
+        .resource_mut::<SaveMedia<CartSave>>()
+        .store_snapshot(&snapshot)
+    {
+        Ok(true) => log::info!("Saved"),
+        Ok(false) => log::error!("Too much fruit to fit in save memory"),
+        Err(err) => log::error!("Couldn't save: {err}"),
+    }
+}
+
+/// The quicksave button on the title screen carries on from the quicksave.
+fn quickload(mut commands: Commands, mut media: ResMut<SaveMedia<CartSave>>) {
+    match media.load_snapshot() {
+        Ok(Some(snapshot)) => commands.queue(LoadSnapshot(snapshot)),
+        Ok(None) => log::info!("No quicksave"),
+        Err(err) => log::error!("Couldn't load quicksave: {err}"),
+    }
 }
@@ -0,0 +1,357 @@
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mel0nPhysicsSet, Mel0nSetupSet, Root,
+    dropper::{Dropper, tick_dropper},
+    fruit::{Collided, Diameter, Fruit, FruitBundle},
+    input::{has_input, next_tick_input},
+    make_root,
+    merge::{MergeData, merge_touching},
+    mode::{GameMode, GameState, start_run},
+    overflow::{FADE_TIME, Fading, OverLine, overflowed},
+    physics::{
+        CollisionData, ImpulseGizmoEvent, Physics, PhysicsConfig, apply_collisions, collide,
+    },
+    replay::{Playback, Recording, Replay, Unreplayable},
+    rules::{GameRules, Overflow},
+    score::Score,
+    wall::constrain_objects,
+};
+
+/// Marks the ghost's root and every fruit in it. The core's systems leave ghost fruit out, and
+/// the ghost has its own to collide, merge and overflow them, so they only ever touch each other.
+#[derive(Component, Default, Debug)]
+pub struct Ghost;
+
+/// Root of the ghost's own hierarchy, beside the player's [`Root`].
+#[derive(Component, Default, Debug)]
+#[require(Ghost, Transform)]
+pub struct GhostRoot;
+
+/// The best run for a mode, kept to race against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BestRun {
+    pub score: u32,
+    pub replay: Replay,
+}
+
+/// Best run for each game mode.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct BestRuns(pub [Option<BestRun>; GameMode::ALL.len()]);
+
+impl BestRuns {
+    #[must_use]
+    pub fn get(&self, mode: GameMode) -> Option<&BestRun> {
+        self.0[mode.index()].as_ref()
+    }
+
+    /// Keeps `run` if it beats the best for `mode`, returning whether it did.
+    pub fn submit(&mut self, mode: GameMode, run: BestRun) -> bool {
+        let best = &mut self.0[mode.index()];
+        if best.as_ref().is_some_and(|best| best.score >= run.score) {
+            return false;
+        }
+        *best = Some(run);
+        true
+    }
+}
+
+/// The best run, playing out beside the live one.
+#[derive(Resource, Debug, Clone)]
+pub struct GhostRun {
+    pub dropper: Dropper,
+    pub score: Score,
+    playback: Playback,
+}
+
+impl GhostRun {
+    #[must_use]
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            dropper: Dropper::new(replay.seed),
+            score: Score::default(),
+            playback: Playback::new(replay),
+        }
+    }
+}
+
+/// Races each run against the best one so far for its mode. Frontends draw the ghost's fruit
+/// by picking up [`Fruit`]s with [`Ghost`] on them.
+pub struct GhostPlugin {
+    /// Where the ghost's arena sits relative to the player's.
+    pub offset: Vec3,
+}
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        let ticking = in_state(GameState::Playing)
+            .and(has_input)
+            .and(resource_exists::<GhostRun>);
+
+        app.init_resource::<BestRuns>()
+            .insert_resource(GhostOffset(self.offset))
+            .add_systems(
+                Startup,
+                spawn_ghost_root.after(make_root).in_set(Mel0nSetupSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                collide_ghost_fruit
+                    .after(apply_collisions)
+                    .before(constrain_objects)
+                    .in_set(Mel0nPhysicsSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    drive_ghost.after(next_tick_input).before(Mel0nPhysicsSet),
+                    (merge_ghost_fruit, check_ghost_overflow, tick_ghost)
+                        .chain()
+                        .after(tick_dropper),
+                )
+                    .run_if(ticking),
+            )
+            .add_systems(OnEnter(GameState::Playing), start_ghost.after(start_run))
+            .add_systems(OnEnter(GameState::GameOver), record_best_run);
+    }
+}
+
+#[derive(Resource)]
+struct GhostOffset(Vec3);
+
+fn spawn_ghost_root(
+    mut commands: Commands,
+    root: Single<&Transform, With<Root>>,
+    offset: Res<GhostOffset>,
+) {
+    let mut transform = **root;
+    transform.translation += offset.0;
+    commands.spawn((Name::new("Ghost root"), GhostRoot, transform));
+}
+
+fn start_ghost(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    best: Res<BestRuns>,
+    playback: Option<Res<Playback>>,
+    rules: Res<GameRules>,
+) {
+    commands.remove_resource::<GhostRun>();
+    // Puzzles start from a layout the ghost doesn't have.
+    if playback.is_some() || *mode == GameMode::Puzzle {
+        return;
+    }
+    // It would play out differently under other physics.
+    if let Some(best) = best
+        .get(*mode)
+        .filter(|best| best.replay.physics == rules.physics)
+    {
+        commands.insert_resource(GhostRun::new(best.replay.clone()));
+    }
+}
+
+/// Plays the ghost's input for this tick, the way the controls drive the player's dropper.
+fn drive_ghost(
+    mut commands: Commands,
+    mut ghost: ResMut<GhostRun>,
+    root: Single<Entity, With<GhostRoot>>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+) {
+    let Some(input) = ghost.playback.next_input() else {
+        return;
+    };
+    let dropper = &mut ghost.dropper;
+    dropper.follow(input, time.delta());
+    if input.hold && rules.hold {
+        dropper.hold();
+    }
+    if input.drop && dropper.can_drop() {
+        let entity = commands
+            .spawn((
+                Ghost,
+                FruitBundle::dropped(dropper.current, dropper.spawn_pos(), rules.physics),
+                Collided(0),
+            ))
+            .id();
+        commands.entity(*root).add_child(entity);
+        dropper.take(entity);
+    }
+}
+
+fn collide_ghost_fruit(
+    mut query: Query<CollisionData, (With<Physics>, With<Fruit>, With<Ghost>)>,
+    config: Res<PhysicsConfig>,
+    mut ev_impulse: EventWriter<ImpulseGizmoEvent>,
+) {
+    collide(&mut query, &config, &mut ev_impulse);
+}
+
+fn merge_ghost_fruit(
+    mut commands: Commands,
+    mut fruits: Query<MergeData, (With<Fruit>, Without<Fading>, With<Ghost>)>,
+    rules: Res<GameRules>,
+    mut ghost: ResMut<GhostRun>,
+) {
+    for merge in merge_touching(&mut commands, &mut fruits) {
+        ghost.score.0 += merge.points(&rules);
+    }
+}
+
+/// Fades the ghost's fruit away as the player's would. Its run ends when its replay does, not
+/// on overflow.
+fn check_ghost_overflow(
+    mut commands: Commands,
+    mut fruits: Query<
+        (Entity, &Transform, &Diameter, &mut OverLine),
+        (With<Fruit>, Without<Fading>, With<Ghost>),
+    >,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+) {
+    if rules.overflow != Overflow::FadeOut {
+        return;
+    }
+    for (entity, transform, diameter, mut over_line) in &mut fruits {
+        if overflowed(transform, *diameter, &mut over_line, time.delta()) {
+            commands
+                .entity(entity)
+                .insert(Fading(Timer::new(FADE_TIME, TimerMode::Once)));
+        }
+    }
+}
+
+fn tick_ghost(
+    mut ghost: ResMut<GhostRun>,
+    fruits: Query<(&Transform, &Diameter), With<Fruit>>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+) {
+    ghost.dropper.tick(time.delta(), &fruits, &rules);
+}
+
+fn record_best_run(
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    recording: Res<Recording>,
+    playback: Option<Res<Playback>>,
+    unreplayable: Res<Unreplayable>,
+    mut best: ResMut<BestRuns>,
+) {
+    if playback.is_some() || unreplayable.0 {
+        return;
+    }
+    let run = BestRun {
+        score: score.0,
+        replay: recording.0.clone(),
+    };
+    if best.submit(*mode, run) {
+        log::info!("New best {} run to race against", mode.name());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use super::{
+        BestRuns, Ghost, GhostRun, collide_ghost_fruit, merge_ghost_fruit, record_best_run,
+    };
+    use crate::{
+        Velocity,
+        fruit::{Collided, Diameter, Fruit, Tier},
+        merge::{ArenaClearEvent, merge_fruit},
+        mode::GameMode,
+        physics::{ImpulseGizmoEvent, Physics, PhysicsConfig, apply_collisions},
+        replay::{Recording, Replay, Unreplayable},
+        rules::GameRules,
+        score::{Score, merge_points},
+    };
+
+    #[test]
+    pub fn ghost_fruit_pass_through_live_fruit() {
+        let mut app = App::new();
+        app.init_resource::<Time<Fixed>>()
+            .init_resource::<PhysicsConfig>()
+            .add_event::<ImpulseGizmoEvent>()
+            .add_systems(Update, (apply_collisions, collide_ghost_fruit));
+
+        // Right on top of each other, and heading into each other.
+        let fruit = |vel| {
+            (
+                Fruit,
+                Physics,
+                Diameter(16.),
+                Collided(0),
+                Velocity(vel),
+                Transform::from_xyz(100., 100., 1.),
+            )
+        };
+        let live = app.world_mut().spawn(fruit(vec2(0., 10.))).id();
+        let ghost = app.world_mut().spawn((fruit(vec2(0., -10.)), Ghost)).id();
+
+        app.update();
+
+        assert_eq!(app.world().get::<Velocity>(live).unwrap().0, vec2(0., 10.));
+        assert_eq!(
+            app.world().get::<Velocity>(ghost).unwrap().0,
+            vec2(0., -10.)
+        );
+        assert_eq!(app.world().get::<Collided>(live).unwrap().0, 0);
+    }
+
+    #[test]
+    pub fn unreplayable_runs_are_not_raced() {
+        for unreplayable in [false, true] {
+            let mut app = App::new();
+            app.insert_resource(GameMode::Classic)
+                .insert_resource(Score(100))
+                .init_resource::<Recording>()
+                .insert_resource(Unreplayable(unreplayable))
+                .init_resource::<BestRuns>()
+                .add_systems(Update, record_best_run);
+            app.update();
+
+            let best = app.world().resource::<BestRuns>();
+            assert_eq!(best.get(GameMode::Classic).is_some(), !unreplayable);
+        }
+    }
+
+    #[test]
+    pub fn ghost_merges_score_for_the_ghost() {
+        let mut app = App::new();
+        app.init_resource::<GameRules>()
+            .init_resource::<Score>()
+            .insert_resource(GhostRun::new(Replay::default()))
+            .add_event::<ArenaClearEvent>()
+            .add_systems(Update, (merge_fruit, merge_ghost_fruit));
+
+        let tier = Tier(2);
+        let fruit = |x| {
+            (
+                Fruit,
+                tier,
+                Diameter(tier.diameter()),
+                Velocity::default(),
+                Transform::from_xyz(x, 100., 1.),
+            )
+        };
+        // A ghost pair, and a live fruit touching one of them.
+        app.world_mut().spawn((fruit(80.), Ghost));
+        app.world_mut().spawn((fruit(80. + tier.diameter()), Ghost));
+        let live = app.world_mut().spawn(fruit(80. - tier.diameter())).id();
+
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.get::<Tier>(live), Some(&tier));
+        assert_eq!(*world.resource::<Score>(), Score(0));
+        assert_eq!(
+            world.resource::<GhostRun>().score,
+            Score(merge_points(Tier(3)))
+        );
+    }
+}
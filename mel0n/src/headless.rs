@@ -0,0 +1,205 @@
+//! The game with no rendering or windowing, for frontends that draw it their own way and for
+//! anything else that drives it tick by tick.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+use crate::{
+    Mel0nBasePlugin, Velocity,
+    dropper::Dropper,
+    fruit::{Diameter, Fruit, Tier},
+    ghost::Ghost,
+    input::{PendingInput, TickInput},
+    mode::{GameMode, GameState},
+    physics::PhysicsConfig,
+    replay::Recording,
+    rules::PhysicsPreset,
+    score::Score,
+    settings::Settings,
+};
+
+/// How a [`HeadlessApp`] run starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeadlessConfig {
+    pub mode: GameMode,
+    pub physics: PhysicsPreset,
+    /// The solver's numbers, for trying out others.
+    pub physics_config: PhysicsConfig,
+    /// Fruit queue seed, in place of the one the run would pick. Puzzles and the daily run bring
+    /// their own fruit, so leave this `None` for them.
+    pub seed: Option<u32>,
+    /// Length of each fixed tick.
+    pub tick: Duration,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Classic,
+            physics: PhysicsPreset::Standard,
+            physics_config: PhysicsConfig::default(),
+            seed: None,
+            tick: Duration::from_millis(16),
+        }
+    }
+}
+
+/// One live fruit, as seen from outside the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiveFruit {
+    pub entity: Entity,
+    pub tier: Tier,
+    /// Arena-space centre.
+    pub pos: Vec2,
+    /// Usually the tier's, but less while fading away.
+    pub diameter: f32,
+    pub velocity: Vec2,
+}
+
+/// Fixed ticks run so far, whatever state the game is in.
+#[derive(Resource, Debug, Default)]
+struct Ticks(u32);
+
+fn count_tick(mut ticks: ResMut<Ticks>) {
+    ticks.0 += 1;
+}
+
+/// A run of the game under [`MinimalPlugins`]. Time only moves when it's stepped, one fixed tick
+/// per update, so the same steps always play out the same.
+pub struct HeadlessApp {
+    app: App,
+}
+
+impl HeadlessApp {
+    /// Sets up the game and starts a run, with nothing in the arena yet.
+    #[must_use]
+    pub fn new(config: HeadlessConfig) -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, Mel0nBasePlugin))
+            .insert_resource(Time::<Fixed>::from_duration(config.tick))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(config.tick))
+            .insert_resource(config.mode)
+            .insert_resource(config.physics_config)
+            .insert_resource(Settings {
+                physics: config.physics,
+                ..default()
+            })
+            .init_resource::<Ticks>()
+            .add_systems(FixedFirst, count_tick);
+        #[cfg(feature = "desktop")]
+        app.init_resource::<ButtonInput<KeyCode>>();
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+
+        if let Some(seed) = config.seed {
+            let world = app.world_mut();
+            *world.resource_mut::<Dropper>() = Dropper::new(seed);
+            world.resource_mut::<Recording>().0.seed = seed;
+        }
+        Self { app }
+    }
+
+    #[must_use]
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    #[must_use]
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    /// Sets what the player does on the next tick.
+    pub fn input(&mut self, input: TickInput) {
+        self.app.world_mut().resource_mut::<PendingInput>().0 = input;
+    }
+
+    /// Runs `ticks` fixed ticks. Does nothing while paused, as no time passes.
+    pub fn step(&mut self, ticks: u32) {
+        if self.paused() {
+            return;
+        }
+        let target = self.ticks() + ticks;
+        while self.ticks() < target {
+            self.app.update();
+        }
+    }
+
+    /// Fixed ticks run since the app started.
+    #[must_use]
+    pub fn ticks(&self) -> u32 {
+        self.world().resource::<Ticks>().0
+    }
+
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        self.world().resource::<Time<Virtual>>().is_paused()
+    }
+
+    /// Pauses the game the way its own pause does, countdown and all.
+    pub fn set_paused(&mut self, paused: bool) {
+        let mut time = self.app.world_mut().resource_mut::<Time<Virtual>>();
+        if paused {
+            time.pause();
+        } else {
+            time.unpause();
+        }
+    }
+
+    #[must_use]
+    pub fn state(&self) -> GameState {
+        *self.world().resource::<State<GameState>>().get()
+    }
+
+    #[must_use]
+    pub fn score(&self) -> u32 {
+        self.world().resource::<Score>().0
+    }
+
+    /// A live fruit, or `None` once it's merged away.
+    #[must_use]
+    pub fn fruit(&self, entity: Entity) -> Option<LiveFruit> {
+        let entity = self.world().get_entity(entity).ok()?;
+        if !entity.contains::<Fruit>() || entity.contains::<Ghost>() {
+            return None;
+        }
+        Some(LiveFruit {
+            entity: entity.id(),
+            tier: *entity.get::<Tier>()?,
+            pos: entity.get::<Transform>()?.translation.xy(),
+            diameter: entity.get::<Diameter>()?.0,
+            velocity: entity.get::<Velocity>()?.0,
+        })
+    }
+
+    /// Every live fruit in the arena, in entity order.
+    #[must_use]
+    pub fn fruits(&mut self) -> Vec<LiveFruit> {
+        let world = self.app.world_mut();
+        let mut query = world.query_filtered::<
+            (Entity, &Tier, &Transform, &Diameter, &Velocity),
+            (With<Fruit>, Without<Ghost>),
+        >();
+        let mut fruits: Vec<_> = query
+            .iter(world)
+            .map(|(entity, &tier, transform, diameter, velocity)| LiveFruit {
+                entity,
+                tier,
+                pos: transform.translation.xy(),
+                diameter: diameter.0,
+                velocity: velocity.0,
+            })
+            .collect();
+        fruits.sort_by_key(|fruit| fruit.entity);
+        fruits
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "desktop")]
+use crate::bindings::KeyBindings;
+use crate::{
+    bindings::ButtonBindings,
+    mode::GameState,
+    replay::{Playback, Recording},
+};
+
+/// What the player did during one fixed tick. Gameplay only sees the controls through this, so a
+/// run can be recorded and played back exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TickInput {
+    /// -1 to move the dropper left, 1 for right.
+    pub dir: i8,
+    pub drop: bool,
+    pub hold: bool,
+    /// Arena x to move the dropper straight to, from pointing at it. Whole units, so replays
+    /// don't depend on how floats get written out.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub aim: Option<i16>,
+}
+
+/// Input read from the controls since the last fixed tick.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct PendingInput(pub TickInput);
+
+/// The input for the fixed tick being run. `None` stops the tick: a replay has run out, or the
+/// run is already over.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct CurrentInput(pub Option<TickInput>);
+
+/// Picks the input for this tick, from the replay being played back or else from the controls.
+pub fn next_tick_input(
+    mut current: ResMut<CurrentInput>,
+    mut pending: ResMut<PendingInput>,
+    mut recording: ResMut<Recording>,
+    playback: Option<ResMut<Playback>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Stop as soon as the run is decided, rather than after however many ticks are left this
+    // frame, so replays end on the same tick.
+    if matches!(*next_state, NextState::Pending(_)) {
+        current.0 = None;
+        return;
+    }
+
+    current.0 = if let Some(mut playback) = playback {
+        let input = playback.next_input();
+        if input.is_none() {
+            log::info!("Replay finished");
+            next_state.set(GameState::GameOver);
+        }
+        input
+    } else {
+        let input = pending.0;
+        pending.0.drop = false;
+        pending.0.hold = false;
+        pending.0.aim = None;
+        recording.0.push(input);
+        Some(input)
+    };
+}
+
+pub(crate) fn has_input(current: Res<CurrentInput>) -> bool {
+    current.0.is_some()
+}
+
+/// Something the player can do. Gameplay and menus only see the controls through these, via
+/// [`ActionState`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Action {
+    #[default]
+    MoveLeft,
+    MoveRight,
+    /// Menus only.
+    MoveUp,
+    /// Menus only.
+    MoveDown,
+    Drop,
+    Hold,
+    Pause,
+    Confirm,
+    Back,
+    /// Saves the run being played, or carries on from the save on the title screen.
+    QuickSave,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Drop,
+        Action::Hold,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::QuickSave,
+    ];
+
+    #[must_use]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Left",
+            Action::MoveRight => "Right",
+            Action::MoveUp => "Up",
+            Action::MoveDown => "Down",
+            Action::Drop => "Drop",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::QuickSave => "Quicksave",
+        }
+    }
+
+    fn in_game(self) -> bool {
+        matches!(
+            self,
+            Action::MoveLeft
+                | Action::MoveRight
+                | Action::Drop
+                | Action::Hold
+                | Action::Pause
+                | Action::QuickSave
+        )
+    }
+
+    fn in_menus(self) -> bool {
+        matches!(
+            self,
+            Action::MoveLeft
+                | Action::MoveRight
+                | Action::MoveUp
+                | Action::MoveDown
+                | Action::Confirm
+                | Action::Back
+                | Action::QuickSave
+        )
+    }
+
+    /// Whether the two are used at the same time, and so can't share a control. Drop and
+    /// confirm can, as one is only used in play and the other only in menus.
+    #[must_use]
+    pub fn clashes_with(self, other: Action) -> bool {
+        self != other
+            && ((self.in_game() && other.in_game()) || (self.in_menus() && other.in_menus()))
+    }
+}
+
+/// Which actions are held down, and which went down this frame.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ActionState {
+    pressed: [bool; Action::ALL.len()],
+    just_pressed: [bool; Action::ALL.len()],
+}
+
+impl ActionState {
+    #[must_use]
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed[action.index()]
+    }
+
+    #[must_use]
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed[action.index()]
+    }
+
+    /// Moves on to the next frame, with `pressed` saying what's held down now.
+    pub fn update(&mut self, pressed: impl Fn(Action) -> bool) {
+        for action in Action::ALL {
+            let i = action.index();
+            let now = pressed(action);
+            self.just_pressed[i] = now && !self.pressed[i];
+            self.pressed[i] = now;
+        }
+    }
+}
+
+/// Run condition for `action` having gone down this frame.
+pub fn action_just_pressed(action: Action) -> impl FnMut(Res<ActionState>) -> bool + Clone {
+    move |actions: Res<ActionState>| actions.just_pressed(action)
+}
+
+/// Without a keyboard, only the gamepad counts; headless, there may not be one at all.
+#[cfg(not(feature = "desktop"))]
+pub fn map_actions(
+    gamepad: Option<Single<&Gamepad>>,
+    buttons: Res<ButtonBindings>,
+    mut actions: ResMut<ActionState>,
+) {
+    actions.update(|action| {
+        gamepad
+            .as_ref()
+            .is_some_and(|g| g.pressed(buttons.button(action)))
+    });
+}
+
+#[cfg(feature = "desktop")]
+pub fn map_actions(
+    gamepad: Option<Single<&Gamepad>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonBindings>,
+    bindings: Res<KeyBindings>,
+    mut actions: ResMut<ActionState>,
+) {
+    actions.update(|action| {
+        gamepad
+            .as_ref()
+            .is_some_and(|g| g.pressed(buttons.button(action)))
+            || keys.pressed(bindings.key(action))
+    });
+}
+
+pub fn read_input(actions: Res<ActionState>, mut pending: ResMut<PendingInput>) {
+    let input = &mut pending.0;
+    input.dir =
+        i8::from(actions.pressed(Action::MoveRight)) - i8::from(actions.pressed(Action::MoveLeft));
+    // Presses stick around until a tick picks them up.
+    input.drop |= actions.just_pressed(Action::Drop);
+    input.hold |= actions.just_pressed(Action::Hold);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, ActionState};
+
+    #[test]
+    pub fn actions_are_just_pressed_for_one_frame() {
+        let mut actions = ActionState::default();
+        actions.update(|action| action == Action::Drop);
+        assert!(actions.pressed(Action::Drop));
+        assert!(actions.just_pressed(Action::Drop));
+        assert!(!actions.pressed(Action::Hold));
+
+        actions.update(|action| action == Action::Drop);
+        assert!(actions.pressed(Action::Drop));
+        assert!(!actions.just_pressed(Action::Drop));
+
+        actions.update(|_| false);
+        assert!(!actions.pressed(Action::Drop));
+    }
+}
@@ -0,0 +1,192 @@
+use alloc::borrow::Cow;
+use core::time::Duration;
+
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dropper::Dropper,
+    fruit::{Fruit, TIER_DIAMETERS, Tier},
+    ghost::Ghost,
+    input::{Action, ActionState},
+    mode::GameState,
+    score::Score,
+    wall::BOTTOM_WALL,
+};
+
+/// How long the arena gets to settle after the last drop before a puzzle counts as failed.
+const SETTLE_TIME: Duration = Duration::from_secs(3);
+
+/// A fruit already in the arena when a puzzle starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LevelFruit {
+    pub tier: Tier,
+    /// Arena-space centre.
+    pub pos: Vec2,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vel: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Goal {
+    /// Make a fruit of at least this tier.
+    MakeTier(Tier),
+    Score(u32),
+}
+
+/// A puzzle: a starting layout, the exact fruit you get to drop, and what to achieve with them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Level {
+    pub name: Cow<'static, str>,
+    pub fruit: Cow<'static, [LevelFruit]>,
+    pub drops: Cow<'static, [Tier]>,
+    pub goal: Goal,
+}
+
+/// A fruit resting on the floor of the arena, centred at `x`.
+const fn on_floor(tier: u8, x: f32) -> LevelFruit {
+    LevelFruit {
+        tier: Tier(tier),
+        pos: Vec2::new(x, BOTTOM_WALL - TIER_DIAMETERS[tier as usize] / 2.),
+        vel: Vec2::ZERO,
+    }
+}
+
+/// Built-in puzzles. The desktop build loads these from RON files instead, see
+/// `bin-desktop/assets/levels`.
+pub static LEVELS: [Level; 3] = [
+    Level {
+        name: Cow::Borrowed("First melon"),
+        fruit: Cow::Borrowed(&[on_floor(8, 90.), on_floor(7, 150.)]),
+        drops: Cow::Borrowed(&[
+            Tier(7),
+            Tier(0),
+            Tier(1),
+            Tier(0),
+            Tier(2),
+            Tier(1),
+            Tier(0),
+            Tier(3),
+            Tier(2),
+            Tier(1),
+        ]),
+        goal: Goal::MakeTier(Tier(9)),
+    },
+    Level {
+        name: Cow::Borrowed("Stepping stones"),
+        fruit: Cow::Borrowed(&[
+            on_floor(0, 70.),
+            on_floor(1, 85.),
+            on_floor(2, 102.),
+            on_floor(3, 120.),
+            on_floor(4, 145.),
+        ]),
+        drops: Cow::Borrowed(&[
+            Tier(0),
+            Tier(1),
+            Tier(2),
+            Tier(3),
+            Tier(4),
+            Tier(0),
+            Tier(1),
+            Tier(2),
+            Tier(3),
+            Tier(4),
+            Tier(0),
+            Tier(1),
+        ]),
+        goal: Goal::Score(150),
+    },
+    Level {
+        name: Cow::Borrowed("Pear pressure"),
+        fruit: Cow::Borrowed(&[on_floor(5, 75.), on_floor(4, 160.)]),
+        drops: Cow::Borrowed(&[
+            Tier(4),
+            Tier(3),
+            Tier(3),
+            Tier(2),
+            Tier(2),
+            Tier(1),
+            Tier(1),
+            Tier(0),
+        ]),
+        goal: Goal::MakeTier(Tier(6)),
+    },
+];
+
+/// Every puzzle that can be played, in order.
+#[derive(Resource, Debug, Clone)]
+pub struct Levels(pub Cow<'static, [Level]>);
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self(Cow::Borrowed(&LEVELS))
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LevelProgress {
+    /// The puzzle played when puzzle mode is picked.
+    pub current: usize,
+    /// Puzzles up to and including this one have been reached.
+    pub unlocked: usize,
+}
+
+/// The puzzle being played. Only exists in puzzle mode.
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveLevel {
+    pub level: Level,
+    pub(crate) settle: Timer,
+}
+
+impl ActiveLevel {
+    #[must_use]
+    pub fn new(level: Level) -> Self {
+        Self {
+            level,
+            settle: Timer::new(SETTLE_TIME, TimerMode::Once),
+        }
+    }
+}
+
+pub fn check_level(
+    mut level: ResMut<ActiveLevel>,
+    fruits: Query<&Tier, (With<Fruit>, Without<Ghost>)>,
+    score: Res<Score>,
+    dropper: Res<Dropper>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let complete = match level.level.goal {
+        Goal::MakeTier(goal) => fruits.iter().any(|&tier| tier >= goal),
+        Goal::Score(goal) => score.0 >= goal,
+    };
+    if complete {
+        next_state.set(GameState::LevelComplete);
+        return;
+    }
+
+    // Out of fruit: give the last drop a chance to land and merge.
+    if dropper.drops_left == Some(0) && dropper.ready && level.settle.tick(time.delta()).finished()
+    {
+        next_state.set(GameState::LevelFailed);
+    }
+}
+
+pub fn advance_level(levels: Res<Levels>, mut progress: ResMut<LevelProgress>) {
+    let last = levels.0.len().saturating_sub(1);
+    progress.current = (progress.current + 1).min(last);
+    progress.unlocked = progress.unlocked.max(progress.current);
+}
+
+pub fn level_menu(actions: Res<ActionState>, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Confirm) {
+        next_state.set(GameState::Playing);
+    } else if actions.just_pressed(Action::Back) {
+        next_state.set(GameState::Title);
+    }
+}
@@ -1,37 +1,83 @@
 //! An example game written in the Bevy game engine and using the [`agb`] crate to allow running it
 //! on the Game Boy Advance.
+//!
+//! Built with neither the `gba` nor the `desktop` feature, it's headless: [`Mel0nBasePlugin`]
+//! runs the whole game under `MinimalPlugins` and `StatesPlugin`, driven through
+//! [`PendingInput`](input::PendingInput). [`headless::HeadlessApp`] sets it up that way, and
+//! `bin-sim` and the terminal frontend, `bin-tui`, run on it.
 
 //! We declare our crate as `no_std`, as the Game Boy Advance doesn't have a port of the standard
 //! library.
 #![no_std]
 
+extern crate alloc;
+
+pub mod bindings;
+pub mod countdown;
+pub mod daily;
+pub mod dropper;
 pub mod fruit;
 #[cfg(feature = "gba")]
 pub mod gba;
+pub mod ghost;
+#[cfg(not(feature = "gba"))]
+pub mod headless;
+pub mod input;
+pub mod level;
+pub mod merge;
+pub mod mode;
+pub mod overflow;
 pub mod physics;
+pub mod replay;
+pub mod rules;
+pub mod save;
+pub mod score;
+pub mod settings;
+pub mod snapshot;
+#[cfg(not(feature = "gba"))]
+pub mod testing;
 pub mod wall;
 
 #[cfg(feature = "gba")]
 pub use agb;
 pub use bevy;
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 #[cfg(feature = "gba")]
 pub use bevy_mod_gba;
 #[cfg(feature = "gba")]
 use bevy_mod_gba::Sprite;
-use fruit::add_fruit;
+use bindings::ControlsPlugin;
+use countdown::{Countdown, tick_countdown};
+#[cfg(feature = "gba")]
+use daily::date_menu;
+use daily::{DailyBests, DailyDate, DateField};
+use dropper::{Dropper, hold_enabled, hold_fruit, move_dropper, tick_dropper};
+use fruit::{Fruit, add_fruit};
 #[cfg(feature = "gba")]
 use gba::Mel0nGbaSetupSet;
+use input::{CurrentInput, PendingInput, has_input, next_tick_input, read_input};
+use level::{ActiveLevel, LevelProgress, Levels, advance_level, check_level, level_menu};
+use merge::{ArenaClearEvent, merge_fruit};
+use mode::{
+    GameMode, GameState, leave_game_over, not_paused, record_high_score, start_run, title_menu,
+    toggle_pause,
+};
+use overflow::{check_overflow, fade_out};
 use physics::{
-    ImpulseGizmoEvent, apply_collisions, apply_friction, apply_gravity, integrate_position,
+    ImpulseGizmoEvent, PhysicsConfig, apply_collisions, apply_friction, apply_gravity,
+    integrate_position,
 };
+use replay::{Playback, Recording, Unreplayable, stop_playback};
+use rules::{GameRules, PhysicsPreset};
+use score::{HighScores, Score};
+use settings::Settings;
+use snapshot::resume;
 use wall::add_walls;
 
 use crate::{fruit::place_fruit, wall::constrain_objects};
 
-const MOON_PHYSICS: bool = false;
 #[derive(Component)]
-#[require(Gravity, Jumps, Velocity, Transform)]
+#[require(Gravity, Velocity, Transform)]
 pub struct Player;
 
 #[derive(Component, Default, Debug)]
@@ -42,12 +88,6 @@ pub struct Gravity;
 #[require(Transform)]
 pub struct Velocity(pub Vec2);
 
-#[derive(Component, Default)]
-pub struct Jumps {
-    current: u8,
-    max: u8,
-}
-
 #[derive(Component)]
 pub struct Collider {
     pub half_size: Vec2,
@@ -56,6 +96,10 @@ pub struct Collider {
 #[cfg(feature = "gba")]
 pub struct Sprites {
     player: Sprite,
+    /// One per frame of the fruit art.
+    fruits: alloc::vec::Vec<Sprite>,
+    /// 0 to 9.
+    digits: alloc::vec::Vec<Sprite>,
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
@@ -66,27 +110,72 @@ pub struct Mel0nPhysicsSet;
 
 pub struct Mel0nBasePlugin;
 
-fn not_moon_physics() -> bool {
-    !MOON_PHYSICS
+fn standard_physics(rules: Res<GameRules>) -> bool {
+    rules.physics == PhysicsPreset::Standard
 }
 
 impl Plugin for Mel0nBasePlugin {
     fn build(&self, app: &mut App) {
         #[cfg(feature = "gba")]
         app.configure_sets(Startup, Mel0nSetupSet.after(Mel0nGbaSetupSet));
+        #[cfg(feature = "desktop")]
+        app.add_plugins(fruit::SandboxPlugin);
+        app.init_resource::<ArenaSpace>();
 
         app.add_systems(
             Startup,
-            (make_root, (add_fruit, add_walls).in_set(Mel0nSetupSet)).chain(),
+            (make_root, add_walls.in_set(Mel0nSetupSet)).chain(),
         );
 
-        app.add_event::<ImpulseGizmoEvent>();
+        app.add_event::<ImpulseGizmoEvent>()
+            .add_event::<ArenaClearEvent>()
+            .init_resource::<GameRules>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<Score>()
+            .init_resource::<Dropper>()
+            .init_resource::<GameMode>()
+            .init_resource::<HighScores>()
+            .init_resource::<Countdown>()
+            .init_resource::<Levels>()
+            .init_resource::<LevelProgress>()
+            .init_resource::<Settings>()
+            .init_resource::<DailyDate>()
+            .init_resource::<DailyBests>()
+            .init_resource::<DateField>()
+            .init_resource::<PendingInput>()
+            .init_resource::<CurrentInput>()
+            .init_resource::<Recording>()
+            .init_resource::<Unreplayable>()
+            .init_state::<GameState>();
+
+        app.add_plugins(ControlsPlugin);
+
+        // Every tick of a run goes through the same input, so it can be replayed exactly.
+        let ticking = in_state(GameState::Playing).and(has_input);
+        app.add_systems(
+            FixedUpdate,
+            next_tick_input.run_if(in_state(GameState::Playing)),
+        );
+        app.configure_sets(
+            FixedUpdate,
+            Mel0nPhysicsSet
+                .after(next_tick_input)
+                .run_if(ticking.clone()),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (move_dropper, hold_fruit.run_if(hold_enabled), place_fruit)
+                .chain()
+                .after(next_tick_input)
+                .before(Mel0nPhysicsSet)
+                .run_if(ticking.clone()),
+        );
 
         app.add_systems(
             FixedUpdate,
             (
-                apply_gravity.run_if(not_moon_physics),
-                apply_friction.run_if(not_moon_physics),
+                apply_gravity.run_if(standard_physics),
+                apply_friction.run_if(standard_physics),
                 integrate_position,
                 apply_collisions,
                 constrain_objects,
@@ -94,45 +183,122 @@ impl Plugin for Mel0nBasePlugin {
                 .chain()
                 .in_set(Mel0nPhysicsSet),
         );
+        app.add_systems(
+            FixedUpdate,
+            (
+                merge_fruit,
+                check_overflow,
+                fade_out,
+                tick_dropper,
+                tick_countdown,
+                check_level.run_if(resource_exists::<ActiveLevel>),
+            )
+                .chain()
+                .after(Mel0nPhysicsSet)
+                .run_if(ticking),
+        );
 
-        app.add_systems(Update, place_fruit);
+        app.add_systems(
+            Update,
+            (
+                read_input.run_if(
+                    in_state(GameState::Playing)
+                        .and(not_paused)
+                        .and(not(resource_exists::<Playback>)),
+                ),
+                toggle_pause.run_if(in_state(GameState::Playing)),
+                title_menu.run_if(in_state(GameState::Title)),
+                leave_game_over.run_if(in_state(GameState::GameOver)),
+                level_menu.run_if(
+                    in_state(GameState::LevelComplete).or(in_state(GameState::LevelFailed)),
+                ),
+            ),
+        );
+        #[cfg(feature = "gba")]
+        app.add_systems(Update, date_menu.run_if(in_state(GameState::PickDate)));
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            (start_run, add_fruit, resume).chain(),
+        )
+        .add_systems(OnEnter(GameState::GameOver), record_high_score)
+        .add_systems(OnEnter(GameState::Title), stop_playback)
+        .add_systems(OnEnter(GameState::LevelComplete), advance_level);
     }
 }
 
 #[derive(Component)]
 pub struct Root;
 
-fn make_root(mut commands: Commands) {
-    commands.spawn((
-        Name::new("Root"),
-        Root,
-        Transform::from_xyz(
-            {
-                #[cfg(feature = "desktop")]
-                {
-                    -110.0
-                }
-                #[cfg(not(feature = "desktop"))]
-                {
-                    0.0
-                }
-            },
-            0.,
-            1.0,
-        )
-        .with_scale(Vec3::new(
-            1.0,
-            {
-                #[cfg(feature = "desktop")]
-                {
-                    -1.0
-                }
-                #[cfg(not(feature = "desktop"))]
-                {
-                    1.0
+/// Where the frontend puts the arena in the world, given to [`Root`] when it's spawned. Arena
+/// coordinates run right and down from the top-left of the play area, on every platform, so
+/// by default the two are the same.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct ArenaSpace(pub Transform);
+
+/// Converts between arena and world coordinates through [`Root`]'s transform. Leaves fruit
+/// out, so systems using it can still move them.
+#[derive(SystemParam)]
+pub struct ArenaCoords<'w> {
+    root: Single<'w, &'static Transform, (With<Root>, Without<Fruit>)>,
+}
+
+impl ArenaCoords<'_> {
+    #[must_use]
+    pub fn to_world(&self, arena: Vec2) -> Vec2 {
+        self.root.transform_point(arena.extend(0.)).truncate()
+    }
+
+    #[must_use]
+    pub fn to_arena(&self, world: Vec2) -> Vec2 {
+        self.root
+            .compute_affine()
+            .inverse()
+            .transform_point3(world.extend(0.))
+            .truncate()
+    }
+
+    /// Where `position` in the window, such as the pointer, is in the arena, seen through
+    /// `camera`.
+    #[cfg(feature = "desktop")]
+    #[must_use]
+    pub fn viewport_to_arena(
+        &self,
+        (camera, camera_transform): (&Camera, &GlobalTransform),
+        position: Vec2,
+    ) -> Option<Vec2> {
+        let world = camera
+            .viewport_to_world_2d(camera_transform, position)
+            .ok()?;
+        Some(self.to_arena(world))
+    }
+}
+
+pub(crate) fn make_root(mut commands: Commands, space: Res<ArenaSpace>) {
+    commands.spawn((Name::new("Root"), Root, space.0));
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+
+    use super::{ArenaCoords, Root};
+
+    #[test]
+    pub fn arena_points_round_trip() {
+        let mut app = App::new();
+        // Flipped, as on the desktop, where the world's y goes up.
+        app.world_mut().spawn((
+            Root,
+            Transform::from_xyz(-110., 0., 1.).with_scale(Vec3::new(1., -1., 1.)),
+        ));
+        app.world_mut()
+            .run_system_once(|coords: ArenaCoords| {
+                for point in [Vec2::ZERO, vec2(62., 147.), vec2(-30., 500.)] {
+                    let back = coords.to_arena(coords.to_world(point));
+                    assert!(back.distance(point) < 1e-4, "{point} came back as {back}");
                 }
-            },
-            1.0,
-        )),
-    ));
+                assert_eq!(coords.to_world(vec2(0., 10.)), vec2(-110., -10.));
+            })
+            .unwrap();
+    }
 }
@@ -0,0 +1,210 @@
+use alloc::vec::Vec;
+
+use bevy::{ecs::query::QueryFilter, prelude::*};
+
+use crate::{
+    Velocity,
+    fruit::{Diameter, Fruit, Tier},
+    ghost::Ghost,
+    overflow::Fading,
+    physics::helpers::bounding_circle,
+    rules::GameRules,
+    score::{Score, merge_points},
+    wall::keep_inside,
+};
+
+/// Fruit resting against each other never quite overlap, so give them a little slack.
+const MERGE_SLOP: f32 = 0.5;
+
+/// Two top-tier fruit met and cleared each other. Frontends can hook this for a celebration.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ArenaClearEvent {
+    /// Arena-space point halfway between the two cleared fruit.
+    pub pos: Vec2,
+    pub bonus: u32,
+}
+
+fn touching(
+    (a_diam, a_trans): (Diameter, &Transform),
+    (b_diam, b_trans): (Diameter, &Transform),
+) -> bool {
+    let a = bounding_circle(a_diam, a_trans.translation);
+    let b = bounding_circle(b_diam, b_trans.translation);
+    a.center.distance(b.center) <= a.radius() + b.radius() + MERGE_SLOP
+}
+
+/// Two fruit that met this tick. The first became `into`, or both cleared if they were already
+/// the top tier.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Merge {
+    /// Arena-space point halfway between the two fruit.
+    pub pos: Vec2,
+    pub into: Option<Tier>,
+}
+
+impl Merge {
+    pub fn points(self, rules: &GameRules) -> u32 {
+        self.into.map_or(rules.melon_pair_bonus, merge_points)
+    }
+}
+
+/// What merging needs of each fruit.
+pub(crate) type MergeData = (
+    Entity,
+    &'static mut Tier,
+    &'static mut Diameter,
+    &'static mut Transform,
+    &'static mut Velocity,
+);
+
+/// Merges the player's touching pairs of fruit and scores them. The ghost's fruit merge the same
+/// way on their own.
+pub fn merge_fruit(
+    mut commands: Commands,
+    mut fruits: Query<MergeData, (With<Fruit>, Without<Fading>, Without<Ghost>)>,
+    rules: Res<GameRules>,
+    mut score: ResMut<Score>,
+    mut ev_clear: EventWriter<ArenaClearEvent>,
+) {
+    for merge in merge_touching(&mut commands, &mut fruits) {
+        score.0 += merge.points(&rules);
+        if merge.into.is_none() {
+            ev_clear.write(ArenaClearEvent {
+                pos: merge.pos,
+                bonus: rules.melon_pair_bonus,
+            });
+        }
+    }
+}
+
+/// Merges each pair of touching fruit of the same tier that `fruits` picks out.
+pub(crate) fn merge_touching<F: QueryFilter>(
+    commands: &mut Commands,
+    fruits: &mut Query<MergeData, F>,
+) -> Vec<Merge> {
+    let pairs: Vec<[Entity; 2]> = fruits
+        .iter_combinations()
+        .filter(|[a, b]| a.1 == b.1 && touching((*a.2, a.3), (*b.2, b.3)))
+        .map(|[a, b]| [a.0, b.0])
+        .collect();
+
+    // Each fruit can only take part in one merge per tick.
+    let mut merged: Vec<Entity> = Vec::new();
+    let mut done = Vec::new();
+
+    for [a, b] in pairs {
+        if merged.contains(&a) || merged.contains(&b) {
+            continue;
+        }
+        let Ok([a, b]) = fruits.get_many_mut([a, b]) else {
+            continue;
+        };
+        let (a_ent, mut tier, mut diam, mut trans, mut vel) = a;
+        let (b_ent, _, _, b_trans, b_vel) = b;
+
+        merged.extend([a_ent, b_ent]);
+
+        let centre = trans.translation.xy().midpoint(b_trans.translation.xy());
+
+        commands.entity(b_ent).despawn();
+
+        let Some(next) = tier.next() else {
+            commands.entity(a_ent).despawn();
+            done.push(Merge {
+                pos: centre,
+                into: None,
+            });
+            continue;
+        };
+
+        *tier = next;
+        diam.0 = next.diameter();
+        // Grown, it might not fit against a wall where the pair was.
+        trans.translation = keep_inside(centre, diam.0).extend(trans.translation.z);
+        trans.scale = Vec2::splat(diam.0).extend(1.);
+        vel.0 = vel.0.midpoint(b_vel.0);
+        done.push(Merge {
+            pos: centre,
+            into: Some(next),
+        });
+    }
+    done
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use bevy::prelude::*;
+
+    use super::{ArenaClearEvent, merge_fruit};
+    use crate::{
+        Velocity,
+        fruit::{Diameter, Fruit, Tier},
+        rules::GameRules,
+        score::{Score, merge_points},
+    };
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<GameRules>()
+            .init_resource::<Score>()
+            .add_event::<ArenaClearEvent>()
+            .add_systems(Update, merge_fruit);
+        app
+    }
+
+    fn spawn(app: &mut App, tier: Tier, pos: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                Fruit,
+                tier,
+                Diameter(tier.diameter()),
+                Velocity::default(),
+                Transform::from_translation(pos.extend(1.)),
+            ))
+            .id()
+    }
+
+    #[test]
+    pub fn top_tier_pair_clears_arena() {
+        let mut app = app();
+        let d = Tier::MAX.diameter();
+        let a = spawn(&mut app, Tier::MAX, vec2(62. + d / 2., 90.));
+        let b = spawn(&mut app, Tier::MAX, vec2(62. + d * 1.5, 90.));
+
+        app.update();
+
+        assert!(app.world().get_entity(a).is_err());
+        assert!(app.world().get_entity(b).is_err());
+
+        let bonus = GameRules::default().melon_pair_bonus;
+        assert_eq!(*app.world().resource::<Score>(), Score(bonus));
+
+        let events = app.world().resource::<Events<ArenaClearEvent>>();
+        let fired: Vec<_> = events.get_cursor().read(events).copied().collect();
+        assert_eq!(
+            fired,
+            [ArenaClearEvent {
+                pos: vec2(62. + d, 90.),
+                bonus,
+            }]
+        );
+    }
+
+    #[test]
+    pub fn lower_tier_pair_grows() {
+        let mut app = app();
+        let tier = Tier(2);
+        let a = spawn(&mut app, tier, vec2(80., 100.));
+        let b = spawn(&mut app, tier, vec2(80. + tier.diameter(), 100.));
+
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.get::<Tier>(a), Some(&Tier(3)));
+        assert!(world.get_entity(b).is_err());
+        assert_eq!(*world.resource::<Score>(), Score(merge_points(Tier(3))));
+        assert!(world.resource::<Events<ArenaClearEvent>>().is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    countdown::Countdown,
+    daily::{DailyBests, DailyDate},
+    dropper::Dropper,
+    fruit::Fruit,
+    input::{Action, ActionState, CurrentInput, PendingInput},
+    level::{ActiveLevel, LevelProgress, Levels},
+    replay::{Playback, Recording, Replay, Unreplayable},
+    rules::{GameRules, Overflow},
+    score::{HighScores, Score},
+    settings::Settings,
+};
+
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// Picking a mode.
+    #[default]
+    Title,
+    Playing,
+    GameOver,
+    LevelComplete,
+    LevelFailed,
+    /// Entering the date for the daily challenge. Only on the GBA, which has no clock.
+    PickDate,
+    /// Changing the controls.
+    Controls,
+}
+
+/// The way of playing picked on the title screen.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameMode {
+    #[default]
+    Classic,
+    /// No game over: overflowing fruit fade away instead.
+    Zen,
+    /// Highest score in two minutes.
+    TimeAttack,
+    /// Preset layouts and drops, with a goal to reach.
+    Puzzle,
+    /// Everyone gets the same fruit on the same day.
+    Daily,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::Zen,
+        GameMode::TimeAttack,
+        GameMode::Puzzle,
+        GameMode::Daily,
+    ];
+
+    #[must_use]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Zen => "Zen",
+            GameMode::TimeAttack => "Time attack",
+            GameMode::Puzzle => "Puzzle",
+            GameMode::Daily => "Daily",
+        }
+    }
+
+    #[must_use]
+    pub fn rules(self) -> GameRules {
+        match self {
+            GameMode::Classic | GameMode::Puzzle | GameMode::Daily => GameRules::default(),
+            GameMode::Zen => GameRules {
+                overflow: Overflow::FadeOut,
+                hold: true,
+                ..default()
+            },
+            // Only the clock ends the run.
+            GameMode::TimeAttack => GameRules {
+                overflow: Overflow::FadeOut,
+                drop_cooldown: Duration::ZERO,
+                time_limit: Some(Duration::from_secs(120)),
+                ..default()
+            },
+        }
+    }
+
+    /// The mode after this one on the title screen, wrapping around.
+    #[must_use]
+    pub fn cycle(self, forward: bool) -> GameMode {
+        let len = Self::ALL.len();
+        let step = if forward { 1 } else { len - 1 };
+        Self::ALL[(self.index() + step) % len]
+    }
+}
+
+/// Clears the arena and sets up a fresh run of the chosen mode.
+pub fn start_run(
+    mut commands: Commands,
+    fruits: Query<Entity, With<Fruit>>,
+    mode: Res<GameMode>,
+    mut rules: ResMut<GameRules>,
+    mut score: ResMut<Score>,
+    mut dropper: ResMut<Dropper>,
+    mut countdown: ResMut<Countdown>,
+    mut time: ResMut<Time<Virtual>>,
+    levels: Res<Levels>,
+    progress: Res<LevelProgress>,
+    daily: Res<DailyDate>,
+    playback: Option<Res<Playback>>,
+    (mut recording, mut unreplayable): (ResMut<Recording>, ResMut<Unreplayable>),
+    (mut pending, mut current): (ResMut<PendingInput>, ResMut<CurrentInput>),
+    real_time: Res<Time<Real>>,
+    settings: Res<Settings>,
+) {
+    for fruit in &fruits {
+        commands.entity(fruit).despawn();
+    }
+    *rules = GameRules {
+        physics: playback
+            .as_ref()
+            .map_or(settings.physics, |playback| playback.replay.physics),
+        ..mode.rules()
+    };
+    *score = Score::default();
+    *countdown = Countdown(rules.time_limit);
+    time.unpause();
+    *pending = PendingInput::default();
+    *current = CurrentInput::default();
+
+    let seed = match (&playback, *mode) {
+        (Some(playback), _) => playback.replay.seed,
+        (None, GameMode::Daily) => daily.0.seed(),
+        (None, _) => {
+            // Anything that varies from run to run will do.
+            let elapsed = real_time.elapsed();
+            #[allow(clippy::cast_possible_truncation)]
+            let secs = elapsed.as_secs() as u32;
+            elapsed.subsec_nanos() ^ secs.rotate_left(16)
+        }
+    };
+    if playback.is_none() {
+        recording.0 = Replay::new(*mode, seed, progress.current, rules.physics);
+    }
+    unreplayable.0 = false;
+
+    commands.remove_resource::<ActiveLevel>();
+    *dropper = match (*mode, levels.0.get(progress.current)) {
+        (GameMode::Puzzle, Some(level)) => {
+            commands.insert_resource(ActiveLevel::new(level.clone()));
+            Dropper::scripted(level.drops.clone())
+        }
+        _ => Dropper::new(seed),
+    };
+}
+
+#[must_use]
+pub fn not_paused(time: Res<Time<Virtual>>) -> bool {
+    !time.is_paused()
+}
+
+fn toggle(time: &mut Time<Virtual>) {
+    if time.is_paused() {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+pub fn record_high_score(
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    mut high_scores: ResMut<HighScores>,
+    daily: Res<DailyDate>,
+    mut daily_bests: ResMut<DailyBests>,
+    playback: Option<Res<Playback>>,
+    unreplayable: Res<Unreplayable>,
+) {
+    // Replays already counted the first time round.
+    if playback.is_some() || unreplayable.0 {
+        return;
+    }
+    if let Some(rank) = high_scores.submit(*mode, score.0) {
+        log::info!("New {} high score #{}: {}", mode.name(), rank + 1, score.0);
+    }
+    if *mode == GameMode::Daily && daily_bests.submit(daily.0, score.0) {
+        log::info!("New best for today's challenge: {}", score.0);
+    }
+}
+
+pub fn title_menu(
+    actions: Res<ActionState>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::MoveDown) {
+        *mode = mode.cycle(true);
+    } else if actions.just_pressed(Action::MoveUp) {
+        *mode = mode.cycle(false);
+    } else if actions.just_pressed(Action::Back) {
+        // A menu action, so it can never share a button with confirm.
+        next_state.set(GameState::Controls);
+    } else if actions.just_pressed(Action::Confirm) {
+        log::info!("Starting {}", mode.name());
+        // Only the GBA needs to be told the date.
+        next_state.set(if *mode == GameMode::Daily && cfg!(feature = "gba") {
+            GameState::PickDate
+        } else {
+            GameState::Playing
+        });
+    }
+}
+
+pub fn toggle_pause(actions: Res<ActionState>, mut time: ResMut<Time<Virtual>>) {
+    if actions.just_pressed(Action::Pause) {
+        toggle(&mut time);
+    }
+}
+
+pub fn leave_game_over(actions: Res<ActionState>, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Confirm) {
+        next_state.set(GameState::Title);
+    }
+}
@@ -0,0 +1,98 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    fruit::{Diameter, Fruit, Tier},
+    ghost::Ghost,
+    level::ActiveLevel,
+    mode::GameState,
+    rules::{GameRules, Overflow},
+};
+
+/// Arena-space y of the danger line. Fruit with their top above it are overflowing.
+pub const DANGER_LINE: f32 = 32.;
+
+/// How long a fruit may sit above the danger line before it counts. Long enough for a fresh drop
+/// to fall through.
+const OVERFLOW_GRACE: Duration = Duration::from_secs(2);
+
+pub(crate) const FADE_TIME: Duration = Duration::from_secs(1);
+
+/// How long this fruit has been above the danger line without a break.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct OverLine(pub Duration);
+
+/// Overflowed in a mode without game over; shrinking away to nothing.
+#[derive(Component, Debug)]
+pub struct Fading(pub(crate) Timer);
+
+/// Ends the run, or fades fruit away, once the player's fruit have been over the danger line for
+/// too long.
+pub fn check_overflow(
+    mut commands: Commands,
+    mut fruits: Query<
+        (Entity, &Transform, &Diameter, &mut OverLine),
+        (With<Fruit>, Without<Fading>, Without<Ghost>),
+    >,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+    level: Option<Res<ActiveLevel>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (entity, transform, diameter, mut over_line) in &mut fruits {
+        if !overflowed(transform, *diameter, &mut over_line, time.delta()) {
+            continue;
+        }
+
+        match rules.overflow {
+            Overflow::GameOver => {
+                next_state.set(if level.is_some() {
+                    GameState::LevelFailed
+                } else {
+                    GameState::GameOver
+                });
+                return;
+            }
+            Overflow::FadeOut => {
+                commands
+                    .entity(entity)
+                    .insert(Fading(Timer::new(FADE_TIME, TimerMode::Once)));
+            }
+        }
+    }
+}
+
+/// Times how long a fruit has been over the danger line, `delta` after the last check, and
+/// whether that's past the grace it gets.
+pub(crate) fn overflowed(
+    transform: &Transform,
+    diameter: Diameter,
+    over_line: &mut OverLine,
+    delta: Duration,
+) -> bool {
+    // Safe while its top is below the line.
+    if transform.translation.y - diameter.0 / 2. >= DANGER_LINE {
+        over_line.0 = Duration::ZERO;
+        return false;
+    }
+    over_line.0 += delta;
+    over_line.0 >= OVERFLOW_GRACE
+}
+
+pub fn fade_out(
+    mut commands: Commands,
+    mut fruits: Query<(Entity, &Tier, &mut Diameter, &mut Transform, &mut Fading)>,
+    time: Res<Time>,
+) {
+    for (entity, tier, mut diameter, mut transform, mut fading) in &mut fruits {
+        if fading.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let size = tier.diameter() * fading.0.fraction_remaining();
+        transform.scale = Vec2::splat(size).extend(1.);
+        diameter.0 = size;
+    }
+}
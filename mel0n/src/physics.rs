@@ -1,17 +1,43 @@
-use core::f32::consts::FRAC_PI_2;
-
-use bevy::{color::palettes::css::GREEN, math::bounding::IntersectsVolume, prelude::*};
-use helpers::bounding_circle;
-use ops::{abs, atan2};
+use bevy::{ecs::query::QueryFilter, math::bounding::IntersectsVolume, prelude::*};
+use helpers::{bounding_circle, penetration};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Gravity, Velocity,
     fruit::{Collided, Diameter, Fruit},
+    ghost::Ghost,
 };
 
-const ELASTICITY: f32 = 0.7;
-pub const MASS: f32 = 16.0;
-const INV_MASS: f32 = 1. / MASS;
+/// The numbers the physics runs on. Every fruit shares them, whatever its tier.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct PhysicsConfig {
+    /// Coefficient of restitution between fruit.
+    pub elasticity: f32,
+    pub mass: f32,
+    /// How much of the overlap between two fruit is undone each tick.
+    pub correction: f32,
+    /// Added to the downward force on each fruit every tick.
+    pub gravity: f32,
+    /// Largest force gravity builds up to.
+    pub terminal_velocity: f32,
+    /// How much of the forces on a fruit carry over to the next tick.
+    pub friction: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            elasticity: 0.7,
+            mass: 16.0,
+            correction: 0.2,
+            gravity: 0.4,
+            terminal_velocity: 20.0,
+            friction: 0.9,
+        }
+    }
+}
 
 #[derive(Event)]
 pub struct CollisionEvent();
@@ -78,7 +104,7 @@ fn resolve_collision(contact: Contact) -> Vec2 {
 pub struct Physics;
 
 #[derive(Component, Default, Debug)]
-pub struct ActingForces(Vec2);
+pub struct ActingForces(pub(crate) Vec2);
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Collision {
@@ -88,19 +114,21 @@ pub enum Collision {
     Bottom,
 }
 
-pub fn apply_gravity(mut entities: Query<&mut ActingForces, With<Gravity>>) {
-    const TERMINAL_VELOCITY: f32 = 20.0;
+pub fn apply_gravity(
+    mut entities: Query<&mut ActingForces, With<Gravity>>,
+    config: Res<PhysicsConfig>,
+) {
+    let limit = config.terminal_velocity;
     for mut acting_forces in &mut entities {
-        acting_forces.0.y = (acting_forces.0.y + 0.4).clamp(-TERMINAL_VELOCITY, TERMINAL_VELOCITY);
+        acting_forces.0.y = (acting_forces.0.y + config.gravity).clamp(-limit, limit);
     }
 }
 
 // Air and ground "friction"
-pub fn apply_friction(mut entities: Query<&mut ActingForces>) {
-    const FRICTION_COEFFICIENT: f32 = 0.9;
+pub fn apply_friction(mut entities: Query<&mut ActingForces>, config: Res<PhysicsConfig>) {
     for mut acting_forces in &mut entities {
-        acting_forces.0.x *= FRICTION_COEFFICIENT;
-        acting_forces.0.y *= FRICTION_COEFFICIENT;
+        acting_forces.0.x *= config.friction;
+        acting_forces.0.y *= config.friction;
     }
 }
 
@@ -127,14 +155,31 @@ pub struct ImpulseGizmoEvent {
     pub mass: f32,
 }
 
+/// What the collision solver needs of each fruit.
+pub(crate) type CollisionData = (
+    &'static mut Transform,
+    &'static Diameter,
+    &'static mut Velocity,
+    &'static mut Collided,
+);
+
+/// Bounces the player's fruit off each other. The ghost's fruit go through the same solver on
+/// their own.
 pub fn apply_collisions(
-    mut query: Query<
-        (&mut Transform, &Diameter, &mut Velocity, &mut Collided),
-        (With<Physics>, With<Fruit>),
-    >,
-    time: Res<Time<Fixed>>,
+    mut query: Query<CollisionData, (With<Physics>, With<Fruit>, Without<Ghost>)>,
+    config: Res<PhysicsConfig>,
     mut ev_impulse: EventWriter<ImpulseGizmoEvent>,
 ) {
+    collide(&mut query, &config, &mut ev_impulse);
+}
+
+/// Resolves every collision between the fruit `query` picks out.
+pub(crate) fn collide<F: QueryFilter>(
+    query: &mut Query<CollisionData, F>,
+    config: &PhysicsConfig,
+    ev_impulse: &mut EventWriter<ImpulseGizmoEvent>,
+) {
+    let mass = config.mass;
     let mut combinations = query.iter_combinations_mut();
     while let Some(
         [
@@ -155,28 +200,30 @@ pub fn apply_collisions(
 
         // log::info!("bop!");
 
-        // let normal_dir = a_trans.translation.xy().angle_to(b_trans.translation.xy());
-        let normal_dir = atan2(
-            b_trans.translation.y - a_trans.translation.y,
-            b_trans.translation.x - a_trans.translation.x,
-        );
-        let normal = Vec2::from_angle(normal_dir).normalize();
-
-        // log::info!("NORM {normal} ({normal_dir})");
+        // Fruit on top of each other have no line between their centres, so push them apart
+        // up and down.
+        let normal = (b_bound.center - a_bound.center)
+            .try_normalize()
+            .unwrap_or(Vec2::Y);
 
         let a = Body {
-            restitution: ELASTICITY,
+            restitution: config.elasticity,
             velocity: a_vel.0,
-            inverse_mass: INV_MASS,
+            inverse_mass: 1. / mass,
         };
         let b = Body {
-            restitution: ELASTICITY,
+            restitution: config.elasticity,
             velocity: b_vel.0,
-            inverse_mass: INV_MASS,
+            inverse_mass: 1. / mass,
         };
 
-        let moving_away =
-            (b_vel.0 - a_vel.0).dot(b_trans.translation.xy() - a_trans.translation.xy()) > 0.0;
+        // Ease overlapping fruit apart by moving them, a share of the overlap each tick. Speeding
+        // them apart instead, Baumgarte style, would add energy.
+        let correction = normal * (config.correction * penetration(a_bound, b_bound) / 2.);
+        a_trans.translation -= correction.extend(0.);
+        b_trans.translation += correction.extend(0.);
+
+        let moving_away = (b_vel.0 - a_vel.0).dot(b_bound.center - a_bound.center) > 0.0;
 
         // Stop "bouncing inwards"
         if moving_away {
@@ -184,70 +231,70 @@ pub fn apply_collisions(
         }
         let impulse = resolve_collision(Contact { normal, a, b });
 
-        // Baumgarte stabilization
-        let penetration_depth = (a_trans.translation.xy() - b_trans.translation.xy()).length()
-            - f32::midpoint(a_diam.0, b_diam.0);
-        // info!("pend {penetration_depth}");
-        let bias_factor = 0.2;
-        let bias = (bias_factor / time.delta().as_secs_f32()) * penetration_depth.abs().max(0.0);
-
         // Draw arrows
         {
             let pos = a_trans.translation.xy();
             ev_impulse.write(ImpulseGizmoEvent {
                 pos,
                 imp: impulse,
-                mass: MASS,
+                mass,
             });
 
             let pos = b_trans.translation.xy();
             ev_impulse.write(ImpulseGizmoEvent {
                 pos,
                 imp: -impulse,
-                mass: MASS,
+                mass,
             });
         }
 
-        a_vel.0 += (impulse + bias) / MASS;
-        b_vel.0 -= (impulse + bias) / MASS;
+        a_vel.0 += impulse / mass;
+        b_vel.0 -= impulse / mass;
     }
 }
 
 pub mod helpers {
-    use bevy::{
-        math::bounding::{Aabb2d, BoundingCircle},
-        prelude::*,
-    };
+    use bevy::math::bounding::{Aabb2d, BoundingCircle};
 
     use crate::{Collider, Vec3, fruit::Diameter};
 
+    /// Fruit are positioned by their centres.
+    #[must_use]
     pub fn bounding_circle(diameter: Diameter, translation: Vec3) -> BoundingCircle {
-        let radius = diameter.0 / 2.;
-        BoundingCircle::new(translation.truncate() + Vec2::splat(radius), radius)
+        BoundingCircle::new(translation.truncate(), diameter.0 / 2.)
     }
 
+    /// How far two bodies overlap along the line between their centres. Zero if they're only
+    /// touching, or apart.
+    #[must_use]
+    pub fn penetration(a: BoundingCircle, b: BoundingCircle) -> f32 {
+        (a.radius() + b.radius() - a.center.distance(b.center)).max(0.)
+    }
+
+    /// Walls are positioned by their centres, too.
+    #[must_use]
     pub fn aabb2d(translation: Vec3, collider: &Collider) -> Aabb2d {
-        Aabb2d::new(
-            translation.truncate() + collider.half_size,
-            collider.half_size,
-        )
+        Aabb2d::new(translation.truncate(), collider.half_size)
     }
 }
 
 #[cfg(test)]
 mod test {
-
-    use core::error::Error;
-
-    use assert_float_eq::assert_float_relative_eq;
+    use assert_float_eq::{assert_float_absolute_eq, assert_float_relative_eq};
     use bevy::{
-        log::info,
-        math::{Vec2, ops::abs, vec2},
-        prelude::Box,
-        reflect::impl_reflect_opaque,
+        math::{Vec2, vec2, vec3},
+        prelude::{App, Transform, Update},
     };
 
-    use super::{Body, Contact, resolve_collision};
+    use super::{
+        Body, Contact, ImpulseGizmoEvent, Physics, PhysicsConfig, apply_collisions,
+        helpers::{bounding_circle, penetration},
+        resolve_collision,
+    };
+    use crate::{
+        Velocity,
+        fruit::{Collided, Diameter, Fruit, Tier},
+    };
 
     #[test]
     pub fn conservation_of_energy() {
@@ -341,4 +388,89 @@ mod test {
         assert_float_relative_eq!(a.velocity.length(), 0.0);
         assert_float_relative_eq!(b.velocity.length(), 0.0);
     }
+
+    #[test]
+    pub fn touching_fruit_do_not_penetrate() {
+        let small = Diameter(Tier(0).diameter());
+        let big = Diameter(Tier(3).diameter());
+        let apart = f32::midpoint(small.0, big.0);
+
+        let a = bounding_circle(small, vec3(80., 60., 1.));
+        let b = bounding_circle(big, vec3(80. + apart, 60., 1.));
+        assert_float_absolute_eq!(penetration(a, b), 0.);
+
+        // Corner to corner, which only works out if positions are centres.
+        let c = bounding_circle(
+            big,
+            (vec2(80., 60.) + Vec2::splat(apart).normalize() * apart).extend(1.),
+        );
+        assert_float_absolute_eq!(penetration(a, c), 0.);
+
+        let d = bounding_circle(big, vec3(80. + apart - 2., 60., 1.));
+        assert_float_relative_eq!(penetration(a, d), 2.);
+    }
+
+    #[test]
+    pub fn touching_fruit_stay_put() {
+        let mut app = App::new();
+        app.init_resource::<PhysicsConfig>()
+            .add_event::<ImpulseGizmoEvent>()
+            .add_systems(Update, apply_collisions);
+
+        let mut spawn = |tier: Tier, pos: Vec2| {
+            app.world_mut()
+                .spawn((
+                    Fruit,
+                    Physics,
+                    Diameter(tier.diameter()),
+                    Velocity::default(),
+                    Collided(0),
+                    Transform::from_translation(pos.extend(1.)),
+                ))
+                .id()
+        };
+        let a = spawn(Tier(1), vec2(100., 100.));
+        let b = spawn(
+            Tier(4),
+            vec2(
+                100.,
+                100. + f32::midpoint(Tier(1).diameter(), Tier(4).diameter()),
+            ),
+        );
+
+        app.update();
+
+        for fruit in [a, b] {
+            let velocity = app.world().get::<Velocity>(fruit).unwrap().0;
+            assert_float_absolute_eq!(velocity.length(), 0.);
+        }
+    }
+
+    #[test]
+    pub fn coincident_fruit_come_apart() {
+        let mut app = App::new();
+        app.init_resource::<PhysicsConfig>()
+            .add_event::<ImpulseGizmoEvent>()
+            .add_systems(Update, apply_collisions);
+        let mut spawn = || {
+            app.world_mut()
+                .spawn((
+                    Fruit,
+                    Physics,
+                    Diameter(Tier(2).diameter()),
+                    Velocity::default(),
+                    Collided(0),
+                    Transform::from_xyz(100., 100., 1.),
+                ))
+                .id()
+        };
+        let a = spawn();
+        let b = spawn();
+
+        app.update();
+
+        let [a, b] = [a, b].map(|fruit| app.world().get::<Transform>(fruit).unwrap().translation);
+        assert!(a.is_finite() && b.is_finite());
+        assert!(a.distance(b) > 0.);
+    }
 }
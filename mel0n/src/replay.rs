@@ -0,0 +1,199 @@
+use alloc::vec::Vec;
+
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::TickInput,
+    level::LevelProgress,
+    mode::{GameMode, GameState},
+    rules::PhysicsPreset,
+};
+
+/// Everything needed to play a run back exactly: how it started and the input for every tick.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Replay {
+    pub mode: GameMode,
+    /// Fruit queue seed.
+    pub seed: u32,
+    /// The puzzle played, in puzzle mode.
+    pub level: usize,
+    /// Replays from before physics presets were Standard.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub physics: PhysicsPreset,
+    /// How many fixed ticks the run lasted.
+    pub ticks: u32,
+    /// The tick input changed on, and what it changed to. Input mostly stays the same from one
+    /// tick to the next, so this is far smaller than a list of every tick.
+    pub changes: Vec<(u32, TickInput)>,
+}
+
+impl Replay {
+    #[must_use]
+    pub fn new(mode: GameMode, seed: u32, level: usize, physics: PhysicsPreset) -> Self {
+        Self {
+            mode,
+            seed,
+            level,
+            physics,
+            ..default()
+        }
+    }
+
+    /// Adds the input for the next tick.
+    pub fn push(&mut self, input: TickInput) {
+        let last = self.changes.last().map_or_else(TickInput::default, |c| c.1);
+        if input != last {
+            self.changes.push((self.ticks, input));
+        }
+        self.ticks += 1;
+    }
+}
+
+/// The run being played, or the last one if it's over.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Recording(pub Replay);
+
+/// Set once something outside the tick input has moved fruit about in the run, like dragging
+/// them in the sandbox. The [`Recording`] no longer plays back the same, so the run doesn't
+/// count for high scores or best runs.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct Unreplayable(pub bool);
+
+/// A replay being played back in place of the controls.
+#[derive(Resource, Debug, Clone)]
+pub struct Playback {
+    pub replay: Replay,
+    tick: u32,
+    change: usize,
+    input: TickInput,
+}
+
+impl Playback {
+    #[must_use]
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0,
+            change: 0,
+            input: TickInput::default(),
+        }
+    }
+
+    /// The input for the next tick, or `None` once the replay is over.
+    pub fn next_input(&mut self) -> Option<TickInput> {
+        if self.tick >= self.replay.ticks {
+            return None;
+        }
+        if let Some(&(_, input)) = self
+            .replay
+            .changes
+            .get(self.change)
+            .filter(|(tick, _)| *tick == self.tick)
+        {
+            self.input = input;
+            self.change += 1;
+        }
+        self.tick += 1;
+        Some(self.input)
+    }
+}
+
+/// Starts playing `replay` back from the beginning.
+pub struct PlayReplay(pub Replay);
+
+impl Command for PlayReplay {
+    fn apply(self, world: &mut World) {
+        let replay = self.0;
+        log::info!(
+            "Playing back {} ticks of {}",
+            replay.ticks,
+            replay.mode.name()
+        );
+        *world.resource_mut::<GameMode>() = replay.mode;
+        world.resource_mut::<LevelProgress>().current = replay.level;
+        world.insert_resource(Playback::new(replay));
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+    }
+}
+
+pub(crate) fn stop_playback(mut commands: Commands) {
+    commands.remove_resource::<Playback>();
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+    use super::{PlayReplay, Recording};
+    use crate::{
+        Mel0nBasePlugin,
+        fruit::Fruit,
+        input::{PendingInput, TickInput},
+        mode::GameState,
+    };
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, Mel0nBasePlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                16,
+            )));
+        #[cfg(feature = "desktop")]
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app
+    }
+
+    fn fruit_bits(app: &mut App) -> Vec<[u32; 3]> {
+        let world = app.world_mut();
+        let mut bits: Vec<_> = world
+            .query_filtered::<&Transform, With<Fruit>>()
+            .iter(world)
+            .map(|transform| transform.translation.to_array().map(f32::to_bits))
+            .collect();
+        bits.sort_unstable();
+        bits
+    }
+
+    #[test]
+    pub fn replay_matches_recording() {
+        let mut recorder = app();
+        recorder
+            .world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        for frame in 0..600 {
+            recorder.world_mut().resource_mut::<PendingInput>().0 = TickInput {
+                dir: if frame / 45 % 2 == 0 { 1 } else { -1 },
+                drop: frame % 25 == 0,
+                hold: false,
+                aim: None,
+            };
+            recorder.update();
+        }
+        let expected = fruit_bits(&mut recorder);
+        let replay = recorder.world().resource::<Recording>().0.clone();
+        assert!(expected.len() > 5);
+
+        let mut player = app();
+        PlayReplay(replay).apply(player.world_mut());
+        for _ in 0..1000 {
+            player.update();
+            if *player.world().resource::<State<GameState>>() == GameState::GameOver {
+                break;
+            }
+        }
+        assert_eq!(
+            *player.world().resource::<State<GameState>>(),
+            GameState::GameOver
+        );
+        assert_eq!(fruit_bits(&mut player), expected);
+    }
+}
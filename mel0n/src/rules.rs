@@ -0,0 +1,96 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// What happens to fruit that stay above the danger line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The run is over.
+    GameOver,
+    /// The fruit gently shrinks away and play carries on.
+    FadeOut,
+}
+
+/// How the physics feels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PhysicsPreset {
+    #[default]
+    Standard,
+    /// No gravity or friction. Fruit are tossed in and drift, bouncing off the walls.
+    Moon,
+}
+
+impl PhysicsPreset {
+    pub const ALL: [PhysicsPreset; 2] = [PhysicsPreset::Standard, PhysicsPreset::Moon];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            PhysicsPreset::Standard => "Standard",
+            PhysicsPreset::Moon => "Moon",
+        }
+    }
+
+    /// Velocity fruit leave the dropper with. Without gravity, it has to carry a fresh drop
+    /// below the danger line before the overflow grace runs out.
+    #[must_use]
+    pub fn drop_velocity(self) -> Vec2 {
+        match self {
+            PhysicsPreset::Standard => Vec2::ZERO,
+            PhysicsPreset::Moon => vec2(0.7, 30.0),
+        }
+    }
+
+    /// Multiplies the velocity of fruit that hit a wall.
+    #[must_use]
+    pub fn wall_restitution(self) -> f32 {
+        match self {
+            PhysicsPreset::Standard => -0.2,
+            PhysicsPreset::Moon => -1.0,
+        }
+    }
+
+    /// How much of a fruit's speed along the floor is left after rolling on it for a second.
+    #[must_use]
+    pub fn floor_grip(self) -> f32 {
+        match self {
+            PhysicsPreset::Standard => 0.1,
+            PhysicsPreset::Moon => 1.0,
+        }
+    }
+}
+
+/// Game-mode config: the knobs that differ between ways of playing.
+#[derive(Resource, Debug, Clone)]
+pub struct GameRules {
+    /// Points awarded when two top-tier fruit merge and clear each other.
+    pub melon_pair_bonus: u32,
+    /// Allow swapping the current fruit into a hold slot. Not in the original game.
+    pub hold: bool,
+    /// Minimum time between drops.
+    pub drop_cooldown: Duration,
+    /// Hold back the next fruit until the last one has fallen out of the spawn zone.
+    pub single_active_drop: bool,
+    pub overflow: Overflow,
+    /// End the run after this long.
+    pub time_limit: Option<Duration>,
+    /// Picked in the settings rather than by the mode, and fixed for the length of a run.
+    pub physics: PhysicsPreset,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            melon_pair_bonus: 1000,
+            hold: false,
+            drop_cooldown: Duration::from_millis(400),
+            single_active_drop: true,
+            overflow: Overflow::GameOver,
+            time_limit: None,
+            physics: PhysicsPreset::Standard,
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::fmt;
+
+use bevy::prelude::*;
+
+use crate::{
+    bindings::ButtonBindings,
+    daily::DailyBests,
+    level::LevelProgress,
+    rules::PhysicsPreset,
+    score::HighScores,
+    settings::{MAX_VOLUME, Settings},
+    snapshot::{Reader, Snapshot, Writer},
+};
+
+/// First bytes of the save file block.
+const MAGIC: [u8; 4] = *b"M0SV";
+
+/// Bumped whenever the save file layout changes. Older saves are reset rather than misread.
+pub const SAVE_VERSION: u8 = 4;
+
+/// How many days' daily challenge bests are saved, latest first, to keep the save file in its
+/// block.
+pub const DAILY_BESTS_KEPT: usize = 100;
+
+/// Where each block lives in save memory. The save file gets more room than it needs so it can
+/// grow without moving the quicksave.
+const SAVE_FILE_OFFSET: usize = 0;
+const SAVE_FILE_LEN: usize = 1024;
+const SNAPSHOT_OFFSET: usize = SAVE_FILE_OFFSET + SAVE_FILE_LEN;
+
+/// Somewhere to keep bytes between sessions: cartridge save memory on the GBA, a file on desktop.
+pub trait SaveBackend {
+    type Error: fmt::Display;
+
+    /// Bytes of save memory available.
+    fn capacity(&self) -> usize;
+
+    /// Fills `buf` from `offset` onwards. Memory that's never been written reads as anything.
+    ///
+    /// # Errors
+    ///
+    /// If the save memory can't be read.
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// # Errors
+    ///
+    /// If the save memory can't be written.
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// What's kept between sessions, apart from the quicksave.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveFile {
+    pub high_scores: HighScores,
+    /// The furthest puzzle reached.
+    pub unlocked: usize,
+    pub settings: Settings,
+    /// Only presets are kept, as they're all the GBA offers.
+    pub buttons: ButtonBindings,
+    /// Only the latest [`DAILY_BESTS_KEPT`] days.
+    pub daily_bests: DailyBests,
+}
+
+impl SaveFile {
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::new());
+        w.bytes(&MAGIC);
+        w.u8(SAVE_VERSION);
+        for table in &self.high_scores.0 {
+            for &score in table {
+                w.u32(score);
+            }
+        }
+        w.index(Some(self.unlocked));
+        w.u8(self.settings.volume);
+        #[allow(clippy::cast_possible_truncation)]
+        w.u8(self.settings.physics as u8);
+        w.index(Some(self.buttons.preset().unwrap_or(0)));
+        let daily_bests = self.daily_bests.0.iter().rev().take(DAILY_BESTS_KEPT);
+        w.index(Some(daily_bests.len()));
+        for (&date, &score) in daily_bests {
+            w.date(date);
+            w.u32(score);
+        }
+        w.0
+    }
+
+    /// `None` if `bytes` isn't a save file from this version of the game.
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut r = Reader(bytes);
+        if r.array::<4>().ok()? != MAGIC || r.u8().ok()? != SAVE_VERSION {
+            return None;
+        }
+        let mut high_scores = HighScores::default();
+        for table in &mut high_scores.0 {
+            for score in table.iter_mut() {
+                *score = r.u32().ok()?;
+            }
+        }
+        let unlocked = r.index().ok()??;
+        let volume = r.u8().ok()?.min(MAX_VOLUME);
+        let physics = *PhysicsPreset::ALL.get(usize::from(r.u8().ok()?))?;
+        let (_, buttons) = *ButtonBindings::PRESETS.get(r.index().ok()??)?;
+        let mut daily_bests = DailyBests::default();
+        for _ in 0..r.index().ok()?? {
+            daily_bests.0.insert(r.date().ok()?, r.u32().ok()?);
+        }
+        Some(Self {
+            high_scores,
+            unlocked,
+            settings: Settings { volume, physics },
+            buttons,
+            daily_bests,
+        })
+    }
+}
+
+/// FNV-1a, to catch saves that have been cut short or scrambled.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// How many bytes follow a block's length header, if a block that long fits in `max_len` along
+/// with its header and checksum. Worked out in `u64`, as blank save memory reads as `0xFF` and
+/// its length would overflow a 32-bit `usize`.
+fn block_len(header: [u8; 4], max_len: usize) -> Option<usize> {
+    let len = u32::from_le_bytes(header);
+    let fits = u64::try_from(max_len).is_ok_and(|max_len| u64::from(len) + 8 <= max_len);
+    fits.then(|| usize::try_from(len).ok()).flatten()
+}
+
+/// The save memory, with the game's layout on top.
+#[derive(Resource, Debug, Clone)]
+pub struct SaveMedia<B>(pub B);
+
+impl<B: SaveBackend> SaveMedia<B> {
+    /// Reads the block at `offset`: its length, the bytes, then their checksum. `None` if
+    /// there's nothing valid there.
+    fn read_block(&mut self, offset: usize, max_len: usize) -> Result<Option<Vec<u8>>, B::Error> {
+        let mut header = [0; 4];
+        self.0.read(offset, &mut header)?;
+        let Some(len) = block_len(header, max_len) else {
+            return Ok(None);
+        };
+        let mut block = vec![0; len + 4];
+        self.0.read(offset + 4, &mut block)?;
+        let (bytes, sum) = block.split_at(len);
+        Ok((sum == checksum(bytes).to_le_bytes()).then(|| bytes.to_vec()))
+    }
+
+    fn write_block(&mut self, offset: usize, bytes: &[u8]) -> Result<(), B::Error> {
+        let len = u32::try_from(bytes.len()).unwrap_or(u32::MAX);
+        let mut block = Vec::with_capacity(bytes.len() + 8);
+        block.extend_from_slice(&len.to_le_bytes());
+        block.extend_from_slice(bytes);
+        block.extend_from_slice(&checksum(bytes).to_le_bytes());
+        self.0.write(offset, &block)
+    }
+
+    /// `None` if there's no save yet, or it's corrupt or from another version.
+    ///
+    /// # Errors
+    ///
+    /// If the save memory can't be read.
+    pub fn load(&mut self) -> Result<Option<SaveFile>, B::Error> {
+        Ok(self
+            .read_block(SAVE_FILE_OFFSET, SAVE_FILE_LEN)?
+            .and_then(|bytes| SaveFile::decode(&bytes)))
+    }
+
+    /// # Errors
+    ///
+    /// If the save memory can't be written.
+    pub fn store(&mut self, save: &SaveFile) -> Result<(), B::Error> {
+        self.write_block(SAVE_FILE_OFFSET, &save.encode())
+    }
+
+    /// `None` if there's no quicksave, or it's corrupt or from another version.
+    ///
+    /// # Errors
+    ///
+    /// If the save memory can't be read.
+    pub fn load_snapshot(&mut self) -> Result<Option<Snapshot>, B::Error> {
+        let max_len = self.0.capacity().saturating_sub(SNAPSHOT_OFFSET);
+        Ok(self
+            .read_block(SNAPSHOT_OFFSET, max_len)?
+            .and_then(|bytes| Snapshot::decode(&bytes).ok()))
+    }
+
+    /// Returns `false` without writing anything if the snapshot doesn't fit.
+    ///
+    /// # Errors
+    ///
+    /// If the save memory can't be written.
+    pub fn store_snapshot(&mut self, snapshot: &Snapshot) -> Result<bool, B::Error> {
+        let bytes = snapshot.encode();
+        if SNAPSHOT_OFFSET + bytes.len() + 8 > self.0.capacity() {
+            return Ok(false);
+        }
+        self.write_block(SNAPSHOT_OFFSET, &bytes)?;
+        Ok(true)
+    }
+}
+
+/// Keeps high scores, puzzle progress, settings and controls in `B` between sessions.
+pub struct SavePlugin<B> {
+    media: Box<dyn Fn(&mut World) -> B + Send + Sync>,
+    settings: bool,
+}
+
+impl<B: Clone + Send + Sync + 'static> SavePlugin<B> {
+    pub fn new(media: B) -> Self {
+        Self {
+            media: Box::new(move |_| media.clone()),
+            settings: true,
+        }
+    }
+}
+
+impl<B: FromWorld + 'static> SavePlugin<B> {
+    /// Makes the media once every plugin is built, for media another plugin sets up.
+    pub fn from_world() -> Self {
+        Self {
+            media: Box::new(B::from_world),
+            settings: true,
+        }
+    }
+}
+
+impl<B> SavePlugin<B> {
+    /// Leaves [`Settings`] and [`ButtonBindings`] alone, for platforms that keep them somewhere
+    /// else. The save file has them at their defaults.
+    #[must_use]
+    pub fn without_settings(mut self) -> Self {
+        self.settings = false;
+        self
+    }
+}
+
+/// Whether settings and controls are kept in the save file.
+#[derive(Resource, Debug, Clone, Copy)]
+struct SavesSettings(bool);
+
+impl<B: SaveBackend + Send + Sync + 'static> Plugin for SavePlugin<B> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SavesSettings(self.settings))
+            .add_systems(PreStartup, load_save::<B>)
+            .add_systems(Last, store_save::<B>);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let media = (self.media)(app.world_mut());
+        app.insert_resource(SaveMedia(media));
+    }
+}
+
+fn load_save<B: SaveBackend + Send + Sync + 'static>(
+    mut media: ResMut<SaveMedia<B>>,
+    saves_settings: Res<SavesSettings>,
+    mut high_scores: ResMut<HighScores>,
+    mut progress: ResMut<LevelProgress>,
+    mut settings: ResMut<Settings>,
+    mut buttons: ResMut<ButtonBindings>,
+    mut daily_bests: ResMut<DailyBests>,
+) {
+    let save = match media.load() {
+        Ok(Some(save)) => save,
+        Ok(None) => {
+            log::info!("No save found, starting fresh");
+            SaveFile::default()
+        }
+        Err(err) => {
+            log::error!("Couldn't read save: {err}");
+            SaveFile::default()
+        }
+    };
+    *high_scores = save.high_scores;
+    progress.unlocked = save.unlocked;
+    progress.current = save.unlocked;
+    if saves_settings.0 {
+        *settings = save.settings;
+        *buttons = save.buttons;
+    }
+    *daily_bests = save.daily_bests;
+}
+
+/// Writes the save whenever something in it changes, and once at startup so a save that was
+/// reset is replaced straight away.
+#[allow(clippy::too_many_arguments)]
+fn store_save<B: SaveBackend + Send + Sync + 'static>(
+    mut media: ResMut<SaveMedia<B>>,
+    saves_settings: Res<SavesSettings>,
+    high_scores: Res<HighScores>,
+    progress: Res<LevelProgress>,
+    settings: Res<Settings>,
+    buttons: Res<ButtonBindings>,
+    daily_bests: Res<DailyBests>,
+    mut last: Local<Option<SaveFile>>,
+) {
+    let changed = high_scores.is_changed()
+        || progress.is_changed()
+        || (saves_settings.0 && (settings.is_changed() || buttons.is_changed()))
+        || daily_bests.is_changed();
+    if last.is_some() && !changed {
+        return;
+    }
+    let mut save = SaveFile {
+        high_scores: high_scores.clone(),
+        unlocked: progress.unlocked,
+        daily_bests: daily_bests.clone(),
+        ..default()
+    };
+    if saves_settings.0 {
+        save.settings = *settings;
+        save.buttons = *buttons;
+    }
+    if last.as_ref() == Some(&save) {
+        return;
+    }
+    if let Err(err) = media.store(&save) {
+        log::error!("Couldn't write save: {err}");
+    }
+    *last = Some(save);
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{vec, vec::Vec};
+    use core::convert::Infallible;
+
+    use super::{
+        DAILY_BESTS_KEPT, SAVE_FILE_LEN, SAVE_FILE_OFFSET, SaveBackend, SaveFile, SaveMedia,
+        block_len,
+    };
+    use crate::{daily::Date, mode::GameMode, rules::PhysicsPreset, settings::Settings};
+
+    /// Save memory that's never been written, as blank SRAM reads.
+    struct Blank(Vec<u8>);
+
+    impl SaveBackend for Blank {
+        type Error = Infallible;
+
+        fn capacity(&self) -> usize {
+            self.0.len()
+        }
+
+        fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Infallible> {
+            buf.copy_from_slice(&self.0[offset..offset + buf.len()]);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Infallible> {
+            self.0[offset..offset + data.len()].copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    fn save() -> SaveFile {
+        let mut save = SaveFile {
+            unlocked: 3,
+            settings: Settings {
+                volume: 4,
+                physics: PhysicsPreset::Moon,
+            },
+            ..Default::default()
+        };
+        save.high_scores.submit(GameMode::Classic, 120);
+        save.high_scores.submit(GameMode::Daily, 80);
+        save.daily_bests.submit(Date::new(2025, 3, 14).unwrap(), 80);
+        save
+    }
+
+    #[test]
+    pub fn save_round_trips() {
+        let mut media = SaveMedia(Blank(vec![0xff; 32 * 1024]));
+        assert_eq!(media.load(), Ok(None));
+        media.store(&save()).unwrap();
+        assert_eq!(media.load(), Ok(Some(save())));
+    }
+
+    #[test]
+    pub fn damaged_saves_are_ignored() {
+        let mut media = SaveMedia(Blank(vec![0; 32 * 1024]));
+        assert_eq!(media.load(), Ok(None));
+
+        media.store(&save()).unwrap();
+        // Flip a bit in the high scores.
+        media.0.0[SAVE_FILE_OFFSET + 12] ^= 1;
+        assert_eq!(media.load(), Ok(None));
+
+        // A save from another version, with a checksum that matches.
+        let mut old = save().encode();
+        old[4] = super::SAVE_VERSION + 1;
+        media.write_block(SAVE_FILE_OFFSET, &old).unwrap();
+        assert_eq!(media.load(), Ok(None));
+    }
+
+    #[test]
+    pub fn only_the_latest_daily_bests_are_kept() {
+        let mut save = save();
+        for days in 0..400 {
+            save.daily_bests
+                .submit(Date::from_unix_days(20_000 + days), days);
+        }
+        let bytes = save.encode();
+        assert!(bytes.len() + 8 <= SAVE_FILE_LEN);
+
+        let loaded = SaveFile::decode(&bytes).unwrap().daily_bests;
+        assert_eq!(loaded.0.len(), DAILY_BESTS_KEPT);
+        assert_eq!(loaded.best(Date::from_unix_days(20_399)), 399);
+        assert_eq!(loaded.best(Date::from_unix_days(20_300)), 300);
+        assert_eq!(loaded.best(Date::from_unix_days(20_299)), 0);
+    }
+
+    #[test]
+    pub fn lengths_past_the_end_are_ignored() {
+        // The largest save memory a 32-bit `usize` can describe, so this holds on the GBA too.
+        let max_len = usize::try_from(u32::MAX).unwrap();
+        assert_eq!(block_len(u32::MAX.to_le_bytes(), max_len), None);
+        // Adding the header and checksum to these wraps round to small numbers in 32 bits.
+        assert_eq!(block_len((u32::MAX - 7).to_le_bytes(), max_len), None);
+        assert_eq!(block_len((u32::MAX - 1).to_le_bytes(), max_len), None);
+        assert_eq!(
+            block_len((u32::MAX - 8).to_le_bytes(), max_len),
+            usize::try_from(u32::MAX - 8).ok()
+        );
+        assert_eq!(block_len(16u32.to_le_bytes(), 24), Some(16));
+        assert_eq!(block_len(17u32.to_le_bytes(), 24), None);
+
+        let mut media = SaveMedia(Blank(vec![0; 4 * 1024]));
+        media.0.0[..4].copy_from_slice(&(u32::MAX - 7).to_le_bytes());
+        assert_eq!(media.load(), Ok(None));
+    }
+}
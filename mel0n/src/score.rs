@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use crate::{fruit::Tier, mode::GameMode};
+
+/// Number of scores kept per mode.
+pub const HIGH_SCORE_ENTRIES: usize = 5;
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Score(pub u32);
+
+/// Points for creating a fruit of `tier` by merging. Triangular numbers, like the original.
+#[must_use]
+pub fn merge_points(tier: Tier) -> u32 {
+    let n = u32::from(tier.0) + 1;
+    n * (n + 1) / 2
+}
+
+/// Best scores for each game mode, highest first.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct HighScores(pub(crate) [[u32; HIGH_SCORE_ENTRIES]; GameMode::ALL.len()]);
+
+impl HighScores {
+    #[must_use]
+    pub fn table(&self, mode: GameMode) -> &[u32; HIGH_SCORE_ENTRIES] {
+        &self.0[mode.index()]
+    }
+
+    #[must_use]
+    pub fn best(&self, mode: GameMode) -> u32 {
+        self.table(mode)[0]
+    }
+
+    /// Adds `score` to the table for `mode`, returning its position if it made the cut.
+    pub fn submit(&mut self, mode: GameMode, score: u32) -> Option<usize> {
+        let table = &mut self.0[mode.index()];
+        let rank = table.iter().position(|&entry| score > entry)?;
+        table[rank..].rotate_right(1);
+        table[rank] = score;
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HighScores;
+    use crate::mode::GameMode;
+
+    #[test]
+    pub fn high_scores_are_per_mode() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.submit(GameMode::Classic, 50), Some(0));
+        assert_eq!(high_scores.submit(GameMode::Classic, 80), Some(0));
+        assert_eq!(high_scores.submit(GameMode::Classic, 60), Some(1));
+        assert_eq!(high_scores.table(GameMode::Classic), &[80, 60, 50, 0, 0]);
+
+        assert_eq!(high_scores.best(GameMode::Zen), 0);
+        assert_eq!(high_scores.submit(GameMode::Zen, 0), None);
+    }
+}
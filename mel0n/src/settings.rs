@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::rules::PhysicsPreset;
+
+pub const MAX_VOLUME: u8 = 10;
+
+/// Player preferences, kept between sessions.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Settings {
+    /// From 0 to [`MAX_VOLUME`].
+    pub volume: u8,
+    /// Used from the next run on.
+    pub physics: PhysicsPreset,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: MAX_VOLUME,
+            physics: PhysicsPreset::Standard,
+        }
+    }
+}
@@ -0,0 +1,695 @@
+use alloc::{borrow::Cow, vec::Vec};
+use core::{fmt, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    Root, Velocity,
+    countdown::Countdown,
+    daily::{DailyDate, Date},
+    dropper::{Dropper, FruitRng, FruitSource},
+    fruit::{Collided, Diameter, Fruit, FruitBundle, Tier},
+    ghost::Ghost,
+    input::TickInput,
+    level::{ActiveLevel, LevelProgress},
+    mode::{GameMode, GameState},
+    overflow::{FADE_TIME, Fading, OverLine},
+    physics::ActingForces,
+    replay::{Playback, Recording, Replay, Unreplayable},
+    rules::{GameRules, PhysicsPreset},
+    score::Score,
+};
+
+/// First bytes of every snapshot.
+const MAGIC: [u8; 4] = *b"MEL0";
+
+/// Bumped whenever the layout changes. Snapshots from other versions are refused rather than
+/// misread.
+pub const SNAPSHOT_VERSION: u8 = 4;
+
+/// Stands in for `None` in fields that are otherwise an index or a count.
+const NONE_U16: u16 = u16::MAX;
+const NONE_TIER: u8 = u8::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// Doesn't start with the snapshot magic bytes.
+    NotASnapshot,
+    /// Written by a different version of the game.
+    Version(u8),
+    /// Ends partway through.
+    Truncated,
+    /// Has a value that doesn't fit the game.
+    Corrupt,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotASnapshot => write!(f, "not a mel0n snapshot"),
+            SnapshotError::Version(version) => write!(
+                f,
+                "snapshot is version {version}, expected {SNAPSHOT_VERSION}"
+            ),
+            SnapshotError::Truncated => write!(f, "snapshot is cut short"),
+            SnapshotError::Corrupt => write!(f, "snapshot is corrupt"),
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+/// One fruit in the arena, as saved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FruitState {
+    pub tier: Tier,
+    /// Arena-space centre.
+    pub pos: Vec2,
+    pub diameter: f32,
+    pub velocity: Vec2,
+    pub forces: Vec2,
+    pub over_line: Duration,
+    /// How far through fading away it is, if it overflowed in a mode without game over.
+    pub fading: Option<Duration>,
+}
+
+/// A run in progress, with everything needed to carry on exactly where it left off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub mode: GameMode,
+    pub level: usize,
+    pub physics: PhysicsPreset,
+    /// The day being played, in the daily challenge.
+    pub date: Date,
+    /// The run so far, so it still plays back from the start once it's finished.
+    pub recording: Replay,
+    pub unreplayable: bool,
+    pub score: Score,
+    pub countdown: Countdown,
+    /// How long a puzzle has been settling after its last drop.
+    pub settle: Option<Duration>,
+    /// Saved without its last drop, which is `last_drop` instead.
+    pub dropper: Dropper,
+    /// Index into `fruit` of the last fruit dropped, while it's still in the way.
+    pub last_drop: Option<usize>,
+    pub fruit: Vec<FruitState>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the run being played. The ghost isn't included.
+    pub fn capture(world: &mut World) -> Self {
+        let mut fruits = world.query_filtered::<(
+            Entity,
+            &Tier,
+            &Transform,
+            &Diameter,
+            &Velocity,
+            &ActingForces,
+            &OverLine,
+            Option<&Fading>,
+        ), (With<Fruit>, Without<Ghost>)>();
+
+        let mut dropper = world.resource::<Dropper>().clone();
+        let mut last_drop = None;
+        let fruit = fruits
+            .iter(world)
+            .enumerate()
+            .map(
+                |(i, (entity, &tier, transform, diameter, velocity, forces, over_line, fading))| {
+                    if dropper.last_drop == Some(entity) {
+                        last_drop = Some(i);
+                    }
+                    FruitState {
+                        tier,
+                        pos: transform.translation.xy(),
+                        diameter: diameter.0,
+                        velocity: velocity.0,
+                        forces: forces.0,
+                        over_line: over_line.0,
+                        fading: fading.map(|fading| fading.0.elapsed()),
+                    }
+                },
+            )
+            .collect();
+        dropper.last_drop = None;
+
+        Self {
+            mode: *world.resource::<GameMode>(),
+            level: world.resource::<LevelProgress>().current,
+            physics: world.resource::<GameRules>().physics,
+            date: world.resource::<DailyDate>().0,
+            recording: world.resource::<Recording>().0.clone(),
+            unreplayable: world.resource::<Unreplayable>().0,
+            score: *world.resource::<Score>(),
+            countdown: *world.resource::<Countdown>(),
+            settle: world
+                .get_resource::<ActiveLevel>()
+                .map(|level| level.settle.elapsed()),
+            dropper,
+            last_drop,
+            fruit,
+        }
+    }
+
+    /// Puts the arena back the way it was saved. Expects a fresh run of the same mode to have
+    /// just been started.
+    fn restore(self, world: &mut World) {
+        let stale: Vec<Entity> = world
+            .query_filtered::<Entity, (With<Fruit>, Without<Ghost>)>()
+            .iter(world)
+            .collect();
+        for entity in stale {
+            world.despawn(entity);
+        }
+        let Ok(root) = world.query_filtered::<Entity, With<Root>>().single(world) else {
+            return;
+        };
+
+        let mut spawned = Vec::with_capacity(self.fruit.len());
+        for fruit in self.fruit {
+            let mut entity = world.spawn((
+                FruitBundle {
+                    tier: fruit.tier,
+                    transform: Transform::from_translation(fruit.pos.extend(1.0))
+                        .with_scale(Vec2::splat(fruit.diameter).extend(1.)),
+                    velocity: Velocity(fruit.velocity),
+                    acting_forces: ActingForces(fruit.forces),
+                    diameter: Diameter(fruit.diameter),
+                    over_line: OverLine(fruit.over_line),
+                    ..default()
+                },
+                Collided(0),
+            ));
+            if let Some(elapsed) = fruit.fading {
+                let mut timer = Timer::new(FADE_TIME, TimerMode::Once);
+                timer.set_elapsed(elapsed);
+                entity.insert(Fading(timer));
+            }
+            let entity = entity.id();
+            world.entity_mut(root).add_child(entity);
+            spawned.push(entity);
+        }
+
+        let mut dropper = self.dropper;
+        dropper.last_drop = self.last_drop.and_then(|i| spawned.get(i).copied());
+        world.insert_resource(dropper);
+        world.resource_mut::<GameRules>().physics = self.physics;
+        world.insert_resource(DailyDate(self.date));
+        world.insert_resource(Recording(self.recording));
+        world.insert_resource(Unreplayable(self.unreplayable));
+        world.insert_resource(self.score);
+        world.insert_resource(self.countdown);
+        if let (Some(elapsed), Some(mut level)) =
+            (self.settle, world.get_resource_mut::<ActiveLevel>())
+        {
+            level.settle.set_elapsed(elapsed);
+        }
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::new());
+        w.bytes(&MAGIC);
+        w.u8(SNAPSHOT_VERSION);
+
+        #[allow(clippy::cast_possible_truncation)]
+        w.u8(self.mode.index() as u8);
+        w.index(Some(self.level));
+        #[allow(clippy::cast_possible_truncation)]
+        w.u8(self.physics as u8);
+        w.date(self.date);
+        let recording = &self.recording;
+        w.u32(recording.seed);
+        w.u32(recording.ticks);
+        w.changes(&recording.changes);
+        w.bool(self.unreplayable);
+        w.u32(self.score.0);
+        w.opt_duration(self.countdown.0);
+        w.opt_duration(self.settle);
+
+        let dropper = &self.dropper;
+        w.f32(dropper.x);
+        w.bool(dropper.ready);
+        w.duration(dropper.since_drop);
+        w.index(self.last_drop);
+        w.u8(dropper.current.0);
+        w.u8(dropper.next.0);
+        w.u8(dropper.held.map_or(NONE_TIER, |tier| tier.0));
+        w.bool(dropper.hold_used);
+        w.index(dropper.drops_left);
+        match &dropper.source {
+            FruitSource::Random(rng) => {
+                w.u8(0);
+                w.u32(rng.0);
+            }
+            FruitSource::Script { tiers, pos } => {
+                w.u8(1);
+                w.index(Some(*pos));
+                w.index(Some(tiers.len()));
+                for tier in tiers.iter() {
+                    w.u8(tier.0);
+                }
+            }
+        }
+
+        w.index(Some(self.fruit.len()));
+        for fruit in &self.fruit {
+            w.u8(fruit.tier.0);
+            w.vec2(fruit.pos);
+            w.f32(fruit.diameter);
+            w.vec2(fruit.velocity);
+            w.vec2(fruit.forces);
+            w.duration(fruit.over_line);
+            w.opt_duration(fruit.fading);
+        }
+        w.0
+    }
+
+    /// # Errors
+    ///
+    /// If `bytes` isn't a snapshot from this version of the game.
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut r = Reader(bytes);
+        if r.array::<4>().ok() != Some(MAGIC) {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let version = r.u8()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(version));
+        }
+
+        let mode = *GameMode::ALL
+            .get(usize::from(r.u8()?))
+            .ok_or(SnapshotError::Corrupt)?;
+        let level = r.index()?.ok_or(SnapshotError::Corrupt)?;
+        let physics = *PhysicsPreset::ALL
+            .get(usize::from(r.u8()?))
+            .ok_or(SnapshotError::Corrupt)?;
+        let date = r.date()?;
+        let seed = r.u32()?;
+        let ticks = r.u32()?;
+        let changes = r.changes()?;
+        let recording = Replay {
+            mode,
+            seed,
+            level,
+            physics,
+            ticks,
+            changes,
+        };
+        let unreplayable = r.bool()?;
+        let score = Score(r.u32()?);
+        let countdown = Countdown(r.opt_duration()?);
+        let settle = r.opt_duration()?;
+
+        let x = r.f32()?;
+        let ready = r.bool()?;
+        let since_drop = r.duration()?;
+        let last_drop = r.index()?;
+        let current = r.tier()?;
+        let next = r.tier()?;
+        let held = match r.u8()? {
+            NONE_TIER => None,
+            tier => Some(Tier(tier)),
+        };
+        let hold_used = r.bool()?;
+        let drops_left = r.index()?;
+        let source = match r.u8()? {
+            0 => FruitSource::Random(FruitRng(r.u32()?)),
+            1 => {
+                let pos = r.index()?.ok_or(SnapshotError::Corrupt)?;
+                let len = r.index()?.ok_or(SnapshotError::Corrupt)?;
+                let tiers = (0..len).map(|_| r.tier()).collect::<Result<Vec<_>, _>>()?;
+                FruitSource::Script {
+                    tiers: Cow::Owned(tiers),
+                    pos,
+                }
+            }
+            _ => return Err(SnapshotError::Corrupt),
+        };
+        let dropper = Dropper {
+            x,
+            ready,
+            since_drop,
+            last_drop: None,
+            current,
+            next,
+            held,
+            hold_used,
+            drops_left,
+            source,
+        };
+
+        let len = r.index()?.ok_or(SnapshotError::Corrupt)?;
+        let fruit = (0..len)
+            .map(|_| {
+                Ok(FruitState {
+                    tier: r.tier()?,
+                    pos: r.vec2()?,
+                    diameter: r.f32()?,
+                    velocity: r.vec2()?,
+                    forces: r.vec2()?,
+                    over_line: r.duration()?,
+                    fading: r.opt_duration()?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if last_drop.is_some_and(|i| i >= fruit.len()) {
+            return Err(SnapshotError::Corrupt);
+        }
+
+        Ok(Self {
+            mode,
+            level,
+            physics,
+            date,
+            recording,
+            unreplayable,
+            score,
+            countdown,
+            settle,
+            dropper,
+            last_drop,
+            fruit,
+        })
+    }
+}
+
+pub(crate) struct Writer(pub(crate) Vec<u8>);
+
+impl Writer {
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) {
+        self.u8(u8::from(value));
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+
+    pub(crate) fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    pub(crate) fn date(&mut self, value: Date) {
+        self.bytes(&value.year.to_le_bytes());
+        self.u8(value.month);
+        self.u8(value.day);
+    }
+
+    pub(crate) fn tick_input(&mut self, value: TickInput) {
+        self.bytes(&value.dir.to_le_bytes());
+        self.bool(value.drop);
+        self.bool(value.hold);
+        self.bool(value.aim.is_some());
+        if let Some(aim) = value.aim {
+            self.bytes(&aim.to_le_bytes());
+        }
+    }
+
+    /// A replay's input changes. A long run has more than an index can count.
+    pub(crate) fn changes(&mut self, changes: &[(u32, TickInput)]) {
+        self.u32(u32::try_from(changes.len()).unwrap_or(u32::MAX));
+        for &(tick, input) in changes {
+            self.u32(tick);
+            self.tick_input(input);
+        }
+    }
+
+    /// Counts and indices are small, so they get two bytes.
+    pub(crate) fn index(&mut self, value: Option<usize>) {
+        let value = value.map_or(NONE_U16, |value| {
+            u16::try_from(value).unwrap_or(NONE_U16 - 1)
+        });
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn duration(&mut self, value: Duration) {
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        self.bytes(&nanos.to_le_bytes());
+    }
+
+    pub(crate) fn opt_duration(&mut self, value: Option<Duration>) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            self.duration(value);
+        }
+    }
+}
+
+pub(crate) struct Reader<'a>(pub(crate) &'a [u8]);
+
+impl Reader<'_> {
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(SnapshotError::Truncated)?;
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Corrupt),
+        }
+    }
+
+    pub(crate) fn tier(&mut self) -> Result<Tier, SnapshotError> {
+        let tier = Tier(self.u8()?);
+        (tier <= Tier::MAX)
+            .then_some(tier)
+            .ok_or(SnapshotError::Corrupt)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32, SnapshotError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    pub(crate) fn vec2(&mut self) -> Result<Vec2, SnapshotError> {
+        Ok(vec2(self.f32()?, self.f32()?))
+    }
+
+    pub(crate) fn date(&mut self) -> Result<Date, SnapshotError> {
+        let year = u16::from_le_bytes(self.array()?);
+        Date::new(year, self.u8()?, self.u8()?).ok_or(SnapshotError::Corrupt)
+    }
+
+    pub(crate) fn tick_input(&mut self) -> Result<TickInput, SnapshotError> {
+        let dir = i8::from_le_bytes(self.array()?);
+        if !(-1..=1).contains(&dir) {
+            return Err(SnapshotError::Corrupt);
+        }
+        Ok(TickInput {
+            dir,
+            drop: self.bool()?,
+            hold: self.bool()?,
+            aim: if self.bool()? {
+                Some(i16::from_le_bytes(self.array()?))
+            } else {
+                None
+            },
+        })
+    }
+
+    pub(crate) fn changes(&mut self) -> Result<Vec<(u32, TickInput)>, SnapshotError> {
+        (0..self.u32()?)
+            .map(|_| Ok((self.u32()?, self.tick_input()?)))
+            .collect()
+    }
+
+    pub(crate) fn index(&mut self) -> Result<Option<usize>, SnapshotError> {
+        match u16::from_le_bytes(self.array()?) {
+            NONE_U16 => Ok(None),
+            value => Ok(Some(usize::from(value))),
+        }
+    }
+
+    pub(crate) fn duration(&mut self) -> Result<Duration, SnapshotError> {
+        Ok(Duration::from_nanos(u64::from_le_bytes(self.array()?)))
+    }
+
+    pub(crate) fn opt_duration(&mut self) -> Result<Option<Duration>, SnapshotError> {
+        if self.bool()? {
+            self.duration().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Carries on a saved run.
+pub struct LoadSnapshot(pub Snapshot);
+
+impl Command for LoadSnapshot {
+    fn apply(self, world: &mut World) {
+        let snapshot = self.0;
+        log::info!(
+            "Resuming {} with {} fruit",
+            snapshot.mode.name(),
+            snapshot.fruit.len()
+        );
+        *world.resource_mut::<GameMode>() = snapshot.mode;
+        world.resource_mut::<LevelProgress>().current = snapshot.level;
+        world.remove_resource::<Playback>();
+        world.insert_resource(Resume(snapshot));
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+    }
+}
+
+/// A snapshot waiting for its run to start.
+#[derive(Resource)]
+pub(crate) struct Resume(Snapshot);
+
+/// Runs once a fresh run has been set up, to swap in the saved one.
+pub(crate) fn resume(world: &mut World) {
+    if let Some(Resume(snapshot)) = world.remove_resource::<Resume>() {
+        snapshot.restore(world);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+    use super::{LoadSnapshot, Snapshot, SnapshotError};
+    use crate::{
+        Mel0nBasePlugin,
+        daily::{DailyDate, Date},
+        dropper::Dropper,
+        fruit::Fruit,
+        input::{PendingInput, TickInput},
+        mode::GameState,
+        replay::Recording,
+        score::Score,
+    };
+
+    const TICK: Duration = Duration::from_millis(16);
+
+    /// One fixed tick per update, so runs line up tick for tick.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, Mel0nBasePlugin))
+            .insert_resource(Time::<Fixed>::from_duration(TICK))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK));
+        #[cfg(feature = "desktop")]
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app
+    }
+
+    fn run_until_tick(app: &mut App, tick: u32) {
+        while app.world().resource::<Recording>().0.ticks < tick {
+            app.update();
+        }
+    }
+
+    fn fruit_bits(app: &mut App) -> Vec<[u32; 3]> {
+        let world = app.world_mut();
+        world
+            .query_filtered::<&Transform, With<Fruit>>()
+            .iter(world)
+            .map(|transform| transform.translation.to_array().map(f32::to_bits))
+            .collect()
+    }
+
+    /// A run that's been going a while, with fruit mid-fall.
+    fn run_in_progress() -> App {
+        let mut app = app();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        for frame in 0..900 {
+            app.world_mut().resource_mut::<PendingInput>().0 = TickInput {
+                dir: if frame / 30 % 2 == 0 { 1 } else { -1 },
+                drop: frame % 10 == 0,
+                hold: false,
+                aim: None,
+            };
+            app.update();
+        }
+        app.world_mut().resource_mut::<PendingInput>().0 = TickInput::default();
+        app
+    }
+
+    #[test]
+    pub fn encoding_round_trips() {
+        let mut app = run_in_progress();
+        let snapshot = Snapshot::capture(app.world_mut());
+        assert!(snapshot.fruit.len() > 5);
+
+        let bytes = snapshot.encode();
+        assert_eq!(Snapshot::decode(&bytes), Ok(snapshot));
+
+        assert_eq!(
+            Snapshot::decode(&bytes[..bytes.len() - 1]),
+            Err(SnapshotError::Truncated)
+        );
+        let mut future = bytes.clone();
+        future[4] += 1;
+        assert_eq!(
+            Snapshot::decode(&future),
+            Err(SnapshotError::Version(super::SNAPSHOT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    pub fn run_carries_on_after_load() {
+        let mut original = run_in_progress();
+        let date = Date::new(2025, 2, 28).unwrap();
+        original.world_mut().resource_mut::<DailyDate>().0 = date;
+        let snapshot = Snapshot::capture(original.world_mut());
+        let bytes = snapshot.encode();
+
+        let saved_at = original.world().resource::<Recording>().0.ticks;
+        run_until_tick(&mut original, saved_at + 200);
+
+        let mut loaded = app();
+        loaded.update();
+        LoadSnapshot(Snapshot::decode(&bytes).unwrap()).apply(loaded.world_mut());
+        run_until_tick(&mut loaded, saved_at + 200);
+
+        assert_eq!(fruit_bits(&mut loaded), fruit_bits(&mut original));
+        assert_eq!(
+            loaded.world().resource::<Score>(),
+            original.world().resource::<Score>()
+        );
+        let dropper = |app: &App| {
+            let mut dropper = app.world().resource::<Dropper>().clone();
+            dropper.last_drop = None;
+            dropper
+        };
+        assert_eq!(dropper(&loaded), dropper(&original));
+        assert_eq!(loaded.world().resource::<DailyDate>().0, date);
+        assert_eq!(
+            loaded.world().resource::<Recording>().0,
+            original.world().resource::<Recording>().0
+        );
+    }
+}
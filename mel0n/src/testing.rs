@@ -0,0 +1,381 @@
+//! Tools for tests, bots and simulations on top of [headless](crate::headless) runs.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt,
+    fmt::Write,
+    ops::{Deref, DerefMut},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    Root, Velocity,
+    dropper::Dropper,
+    fruit::{Collided, FruitBundle, Tier},
+    headless::{HeadlessApp, HeadlessConfig},
+    input::TickInput,
+    rules::PhysicsPreset,
+};
+
+/// Fruit that stay within [`SETTLE_SLOP`] of where they were for this many ticks have come to
+/// rest.
+const SETTLE_TICKS: u32 = 30;
+/// A pile at rest can still jitter back and forth by a few hundredths of a unit, never
+/// settling to the exact spot.
+const SETTLE_SLOP: f32 = 0.1;
+
+/// Every `E` sent since [`SimApp::watch`] was called for it.
+#[derive(Resource)]
+struct Seen<E>(Vec<E>);
+
+fn see<E: Event + Clone>(mut events: EventReader<E>, mut seen: ResMut<Seen<E>>) {
+    seen.0.extend(events.read().cloned());
+}
+
+/// A [`HeadlessApp`] with what tests and bots need on top: fruit placed by hand, waiting for
+/// the arena to settle, and traces of where every fruit went.
+pub struct SimApp(HeadlessApp);
+
+impl Default for SimApp {
+    fn default() -> Self {
+        Self::new(HeadlessConfig::default())
+    }
+}
+
+impl Deref for SimApp {
+    type Target = HeadlessApp;
+
+    fn deref(&self) -> &HeadlessApp {
+        &self.0
+    }
+}
+
+impl DerefMut for SimApp {
+    fn deref_mut(&mut self) -> &mut HeadlessApp {
+        &mut self.0
+    }
+}
+
+impl SimApp {
+    /// Sets up the game and starts a run, with nothing in the arena yet.
+    #[must_use]
+    pub fn new(config: HeadlessConfig) -> Self {
+        Self(HeadlessApp::new(config))
+    }
+
+    /// Puts a fruit of `tier` centred on `pos` in the arena, already moving at `velocity`.
+    ///
+    /// # Panics
+    ///
+    /// If the arena was never set up.
+    pub fn spawn_fruit(&mut self, tier: Tier, pos: Vec2, velocity: Vec2) -> Entity {
+        let world = self.app_mut().world_mut();
+        let root = world
+            .query_filtered::<Entity, With<Root>>()
+            .single(world)
+            .expect("the arena is set up");
+        let fruit = FruitBundle {
+            velocity: Velocity(velocity),
+            ..FruitBundle::dropped(tier, pos, PhysicsPreset::Standard)
+        };
+        world.spawn((fruit, Collided(0), ChildOf(root))).id()
+    }
+
+    /// Waits up to `patience` ticks for the dropper to be ready, then drops its fruit centred
+    /// on `x`. `None` if it's still not ready, or the run ended first.
+    pub fn drop_at(&mut self, x: f32, patience: u32) -> Option<Entity> {
+        if !self.step_until(patience, |sim| sim.world().resource::<Dropper>().can_drop()) {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        self.input(TickInput {
+            drop: true,
+            aim: Some(ops::round(x) as i16),
+            ..default()
+        });
+        self.step(1);
+        self.world().resource::<Dropper>().last_drop
+    }
+
+    /// Runs ticks until `done`, for at most `max` of them. Whether `done` was reached.
+    pub fn step_until(&mut self, max: u32, mut done: impl FnMut(&mut Self) -> bool) -> bool {
+        for _ in 0..max {
+            if done(self) {
+                return true;
+            }
+            self.step(1);
+        }
+        done(self)
+    }
+
+    /// Runs ticks until every fruit has come to rest, for at most `max` of them. Whether they
+    /// did.
+    pub fn settle(&mut self, max: u32) -> bool {
+        let mut still_since = self.ticks();
+        let mut rest = self.fruits();
+        for _ in 0..max {
+            self.step(1);
+            let now = self.fruits();
+            let still = now.len() == rest.len()
+                && rest.iter().zip(&now).all(|(rest, now)| {
+                    rest.entity == now.entity && rest.pos.distance(now.pos) < SETTLE_SLOP
+                });
+            if !still {
+                still_since = self.ticks();
+                rest = now;
+            } else if self.ticks() - still_since >= SETTLE_TICKS {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Starts keeping every `E` sent from now on, for [`events`](Self::events).
+    pub fn watch<E: Event + Clone>(&mut self) -> &mut Self {
+        if !self.world().contains_resource::<Seen<E>>() {
+            self.app_mut()
+                .insert_resource(Seen::<E>(Vec::new()))
+                .add_systems(Last, see::<E>);
+        }
+        self
+    }
+
+    /// Every `E` sent since it was first [watched](Self::watch).
+    ///
+    /// # Panics
+    ///
+    /// If `E` isn't being watched.
+    #[must_use]
+    pub fn events<E: Event + Clone>(&self) -> &[E] {
+        &self
+            .world()
+            .get_resource::<Seen<E>>()
+            .expect("events are watched before they're read")
+            .0
+    }
+
+    /// Runs `ticks` ticks, noting where every fruit is after each. `each_tick` gets to set the
+    /// input before each tick, given how many have been recorded so far.
+    pub fn record(&mut self, ticks: u32, mut each_tick: impl FnMut(&mut Self, u32)) -> Trace {
+        let mut ids = FruitIds::default();
+        let mut trace = Trace::default();
+        for tick in 0..ticks {
+            each_tick(self, tick);
+            self.step(1);
+            let fruits = self
+                .fruits()
+                .into_iter()
+                .map(|fruit| TracedFruit {
+                    id: ids.of(fruit.entity),
+                    tier: fruit.tier,
+                    pos: fruit.pos,
+                })
+                .collect();
+            trace.ticks.push(fruits);
+        }
+        trace
+    }
+}
+
+/// Numbers fruit in the order they first turn up, so a run's output doesn't depend on entities.
+#[derive(Debug, Clone, Default)]
+pub struct FruitIds(Vec<Entity>);
+
+impl FruitIds {
+    /// The number of `entity`, given the next one free if it's not been seen before.
+    pub fn of(&mut self, entity: Entity) -> u32 {
+        let id = self.0.iter().position(|&e| e == entity).unwrap_or_else(|| {
+            self.0.push(entity);
+            self.0.len() - 1
+        });
+        #[allow(clippy::cast_possible_truncation)]
+        let id = id as u32;
+        id
+    }
+}
+
+/// One fruit on one tick of a [`Trace`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TracedFruit {
+    /// From [`FruitIds`].
+    pub id: u32,
+    pub tier: Tier,
+    pub pos: Vec2,
+}
+
+/// Where every fruit was after each tick of a run, from [`SimApp::record`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    /// Ordered by id within each tick.
+    pub ticks: Vec<Vec<TracedFruit>>,
+}
+
+/// Where a [`Trace`] first parted ways with the one it was checked against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Divergence {
+    pub tick: usize,
+    pub fruit: u32,
+    pub expected: Option<TracedFruit>,
+    pub actual: Option<TracedFruit>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |fruit: Option<TracedFruit>| {
+            fruit.map_or_else(
+                || String::from("nothing"),
+                |fruit| alloc::format!("a {} at {}", fruit.tier.name(), fruit.pos),
+            )
+        };
+        write!(
+            f,
+            "fruit {} diverged first, on tick {}: expected {}, got {}",
+            self.fruit,
+            self.tick,
+            describe(self.expected),
+            describe(self.actual)
+        )
+    }
+}
+
+/// Why a [`Trace`] couldn't be read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceError {
+    pub line: usize,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trace line {} isn't tick,fruit,tier,x,y", self.line)
+    }
+}
+
+impl core::error::Error for TraceError {}
+
+impl Trace {
+    const HEADER: &str = "tick,fruit,tier,x,y";
+
+    /// The first fruit, on the first tick, that's in a different place from in `expected` by
+    /// more than `tolerance`, or a different tier, or only in one of them.
+    #[must_use]
+    pub fn diverges_from(&self, expected: &Trace, tolerance: f32) -> Option<Divergence> {
+        let ticks = self.ticks.len().max(expected.ticks.len());
+        (0..ticks).find_map(|tick| {
+            let actual = self.ticks.get(tick).map_or(&[][..], Vec::as_slice);
+            let expected = expected.ticks.get(tick).map_or(&[][..], Vec::as_slice);
+            let find = |fruits: &[TracedFruit], id| fruits.iter().copied().find(|f| f.id == id);
+            let mut ids: Vec<u32> = actual.iter().chain(expected).map(|f| f.id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids.into_iter().find_map(|id| {
+                let (expected, actual) = (find(expected, id), find(actual, id));
+                let same = match (expected, actual) {
+                    (Some(e), Some(a)) => e.tier == a.tier && e.pos.distance(a.pos) <= tolerance,
+                    _ => false,
+                };
+                (!same).then_some(Divergence {
+                    tick,
+                    fruit: id,
+                    expected,
+                    actual,
+                })
+            })
+        })
+    }
+
+    /// One line per fruit per tick, under a header. Positions are written exactly.
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(Self::HEADER);
+        csv.push('\n');
+        for (tick, fruits) in self.ticks.iter().enumerate() {
+            for fruit in fruits {
+                // Writing to a `String` can't fail.
+                let _ = writeln!(
+                    csv,
+                    "{tick},{},{},{},{}",
+                    fruit.id, fruit.tier.0, fruit.pos.x, fruit.pos.y
+                );
+            }
+        }
+        csv
+    }
+
+    /// Reads back a trace written by [`to_csv`](Self::to_csv). Ticks with no fruit left at the
+    /// end aren't written, but they compare the same as missing ones.
+    ///
+    /// # Errors
+    ///
+    /// If a line isn't a fruit.
+    pub fn from_csv(csv: &str) -> Result<Self, TraceError> {
+        let mut trace = Trace::default();
+        for (line, text) in csv.lines().enumerate() {
+            if line == 0 && text == Self::HEADER || text.is_empty() {
+                continue;
+            }
+            let error = || TraceError { line: line + 1 };
+            let mut fields = text.split(',');
+            let mut field = || fields.next().ok_or_else(error);
+            let tick: usize = field()?.parse().map_err(|_| error())?;
+            let id = field()?.parse().map_err(|_| error())?;
+            let tier = field()?.parse().map_err(|_| error())?;
+            let x = field()?.parse().map_err(|_| error())?;
+            let y = field()?.parse().map_err(|_| error())?;
+            if tick >= trace.ticks.len() {
+                trace.ticks.resize(tick + 1, Vec::new());
+            }
+            trace.ticks[tick].push(TracedFruit {
+                id,
+                tier: Tier(tier),
+                pos: vec2(x, y),
+            });
+        }
+        Ok(trace)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fruit(id: u8, x: f32) -> TracedFruit {
+        TracedFruit {
+            id: id.into(),
+            tier: Tier(id),
+            pos: vec2(x, 0.5),
+        }
+    }
+
+    #[test]
+    pub fn csv_round_trips() {
+        let trace = Trace {
+            ticks: vec![vec![fruit(0, 1.25)], vec![fruit(0, 1.5), fruit(1, -3.)]],
+        };
+        assert_eq!(Trace::from_csv(&trace.to_csv()), Ok(trace));
+        assert_eq!(
+            Trace::from_csv("tick,fruit,tier,x,y\n0,0,1,2\n"),
+            Err(TraceError { line: 2 })
+        );
+    }
+
+    #[test]
+    pub fn first_divergence_is_reported() {
+        let expected = Trace {
+            ticks: vec![vec![fruit(0, 1.)], vec![fruit(0, 1.), fruit(1, 2.)]],
+        };
+        let mut actual = expected.clone();
+        assert_eq!(actual.diverges_from(&expected, 0.1), None);
+
+        actual.ticks[1][1].pos.x += 0.05;
+        assert_eq!(actual.diverges_from(&expected, 0.1), None);
+
+        actual.ticks[1][1].pos.x += 0.1;
+        let divergence = actual.diverges_from(&expected, 0.1).unwrap();
+        assert_eq!((divergence.tick, divergence.fruit), (1, 1));
+
+        actual.ticks[1].pop();
+        let divergence = actual.diverges_from(&expected, 0.1).unwrap();
+        assert_eq!(divergence.actual, None);
+    }
+}
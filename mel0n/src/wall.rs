@@ -1,17 +1,18 @@
 use bevy::prelude::*;
 
 use crate::{
-    Collider, MOON_PHYSICS, Velocity,
+    Collider, Velocity,
     fruit::{Diameter, Fruit},
     physics::Physics,
+    rules::GameRules,
 };
 
-const WALL_THICKNESS: f32 = 1.;
-const LEFT_WALL: f32 = 62.;
-const RIGHT_WALL: f32 = 179. - WALL_THICKNESS;
+pub const WALL_THICKNESS: f32 = 1.;
+pub const LEFT_WALL: f32 = 62.;
+pub const RIGHT_WALL: f32 = 179. - WALL_THICKNESS;
 // y coordinates
-const BOTTOM_WALL: f32 = 148. - WALL_THICKNESS;
-const TOP_WALL: f32 = 0.;
+pub const BOTTOM_WALL: f32 = 148. - WALL_THICKNESS;
+pub const TOP_WALL: f32 = 0.;
 
 // This is a collection of the components that define a "Wall" in our game
 #[derive(Component, Debug)]
@@ -27,13 +28,17 @@ pub enum WallLocation {
 }
 
 impl WallLocation {
-    /// Location of the *center* of the wall, used in `transform.translation()`
+    /// Location of the *center* of the wall, used in `transform.translation()`. Each wall sits
+    /// just outside the space fruit are kept in.
     fn position(&self) -> Vec2 {
+        let middle_x = f32::midpoint(LEFT_WALL, RIGHT_WALL);
+        let middle_y = f32::midpoint(TOP_WALL, BOTTOM_WALL);
+        let half = WALL_THICKNESS / 2.;
         match self {
-            WallLocation::Left => Vec2::new(LEFT_WALL, 0.),
-            WallLocation::Right => Vec2::new(RIGHT_WALL, 0.),
-            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL),
-            WallLocation::Top => Vec2::new(0., TOP_WALL),
+            WallLocation::Left => Vec2::new(LEFT_WALL - half, middle_y),
+            WallLocation::Right => Vec2::new(RIGHT_WALL + half, middle_y),
+            WallLocation::Bottom => Vec2::new(middle_x, BOTTOM_WALL + half),
+            WallLocation::Top => Vec2::new(middle_x, TOP_WALL - half),
         }
     }
 
@@ -46,11 +51,12 @@ impl WallLocation {
         assert!(arena_width > 0.0);
 
         match self {
+            // Long enough to meet the others at the corners.
             WallLocation::Left | WallLocation::Right => {
-                Vec2::new(WALL_THICKNESS, arena_height + WALL_THICKNESS)
+                Vec2::new(WALL_THICKNESS, arena_height + 2. * WALL_THICKNESS)
             }
             WallLocation::Bottom | WallLocation::Top => {
-                Vec2::new(arena_width + WALL_THICKNESS, WALL_THICKNESS)
+                Vec2::new(arena_width + 2. * WALL_THICKNESS, WALL_THICKNESS)
             }
         }
     }
@@ -84,22 +90,45 @@ pub fn add_walls(mut commands: Commands) {
     commands.spawn(Wall::new(WallLocation::Top));
 }
 
-pub fn constrain_objects(query: Query<(&mut Transform, &mut Velocity, &Diameter), With<Fruit>>) {
+/// The nearest point to `pos` that a fruit `diameter` across can be centred on without poking
+/// through a wall.
+#[must_use]
+pub fn keep_inside(pos: Vec2, diameter: f32) -> Vec2 {
+    let radius = diameter / 2.;
+    vec2(
+        pos.x.clamp(LEFT_WALL + radius, RIGHT_WALL - radius),
+        pos.y.clamp(TOP_WALL + radius, BOTTOM_WALL - radius),
+    )
+}
+
+pub fn constrain_objects(
+    query: Query<(&mut Transform, &mut Velocity, &Diameter), With<Fruit>>,
+    rules: Res<GameRules>,
+    time: Res<Time>,
+) {
     // log::info!("bwuh");
 
-    const WALL_RESTITUTION: f32 = const { if MOON_PHYSICS { -1.0 } else { -0.2 } };
+    let wall_restitution = rules.physics.wall_restitution();
+    let floor_grip = ops::powf(rules.physics.floor_grip(), time.delta_secs());
 
     for (mut ts, mut vl, dm) in query {
         // log::info!("guh {:?}", ts.0.translation);
 
-        if ts.translation.x != ts.translation.x.clamp(LEFT_WALL, RIGHT_WALL - dm.0) {
-            vl.0.x *= WALL_RESTITUTION;
+        let inside = keep_inside(ts.translation.xy(), dm.0);
+        let pushed = inside - ts.translation.xy();
+
+        if pushed.x != 0. {
+            vl.0.x *= wall_restitution;
+        }
+        if pushed.y != 0. {
+            vl.0.y *= wall_restitution;
         }
-        if ts.translation.y != ts.translation.y.clamp(TOP_WALL, BOTTOM_WALL - dm.0) {
-            vl.0.y *= WALL_RESTITUTION;
+        // Pushed back up out of the floor, so it's rolling along it.
+        if pushed.y < 0. {
+            vl.0.x *= floor_grip;
         }
 
-        ts.translation.x = ts.translation.x.clamp(LEFT_WALL, RIGHT_WALL - dm.0);
-        ts.translation.y = ts.translation.y.clamp(TOP_WALL, BOTTOM_WALL - dm.0);
+        ts.translation.x = inside.x;
+        ts.translation.y = inside.y;
     }
 }
@@ -0,0 +1,101 @@
+//! Runs fixed scenarios and checks every fruit's path against the traces checked in under
+//! `tests/golden`, so changes to the physics can't slip by unnoticed. When a change is meant to
+//! alter them, regenerate the traces with `just goldens`, or:
+//!
+//! ```sh
+//! UPDATE_GOLDENS=1 cargo test -p mel0n --test golden
+//! ```
+
+use std::{env, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use mel0n::{
+    fruit::Tier,
+    headless::HeadlessConfig,
+    input::TickInput,
+    rules::PhysicsPreset,
+    testing::{SimApp, Trace},
+    wall::{BOTTOM_WALL, LEFT_WALL, RIGHT_WALL},
+};
+
+/// How far a fruit can stray from its golden path before it counts as a change, to allow for
+/// floats coming out a little differently on other platforms.
+const TOLERANCE: f32 = 1e-3;
+
+fn golden(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("csv")
+}
+
+/// Compares `trace` with the golden one called `name`, or replaces it with `trace` when
+/// regenerating.
+fn check(name: &str, trace: &Trace) {
+    let path = golden(name);
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, trace.to_csv()).unwrap();
+        return;
+    }
+    let csv = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!("no golden trace at {}: {err}", path.display());
+    });
+    let expected = Trace::from_csv(&csv).unwrap();
+    if let Some(divergence) = trace.diverges_from(&expected, TOLERANCE) {
+        panic!("{name}: {divergence}");
+    }
+}
+
+/// Drops fruit as fast as the dropper allows, moving it across the arena in uneven steps every
+/// `every` ticks.
+fn sweep(every: u32) -> impl FnMut(&mut SimApp, u32) {
+    let width = RIGHT_WALL - LEFT_WALL;
+    move |sim, tick| {
+        let step = (tick / every * 37) % 100;
+        #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+        let x = (LEFT_WALL + width * step as f32 / 100.) as i16;
+        sim.input(TickInput {
+            drop: true,
+            aim: Some(x),
+            ..default()
+        });
+    }
+}
+
+#[test]
+fn standard_drops() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        seed: Some(1),
+        ..default()
+    });
+    let trace = sim.record(600, sweep(40));
+    check("standard_drops", &trace);
+}
+
+#[test]
+fn moon_drops() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        physics: PhysicsPreset::Moon,
+        seed: Some(2),
+        ..default()
+    });
+    let trace = sim.record(600, sweep(40));
+    check("moon_drops", &trace);
+}
+
+#[test]
+fn tumbling_pile() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        seed: Some(3),
+        ..default()
+    });
+    for (i, tier) in [3, 1, 4, 1, 5, 2, 6, 0].into_iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let i = i as f32;
+        let pos = vec2(LEFT_WALL + 14. + i * 13., BOTTOM_WALL - 20. - i * 9.);
+        sim.spawn_fruit(Tier(tier), pos, vec2(30. - i * 8., 0.));
+    }
+    let trace = sim.record(400, |_, _| {});
+    check("tumbling_pile", &trace);
+}
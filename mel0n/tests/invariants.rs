@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use mel0n::{
+    Velocity,
+    fruit::{Fruit, TIER_DIAMETERS, Tier},
+    headless::{HeadlessConfig, LiveFruit},
+    mode::GameMode,
+    physics::{apply_collisions, integrate_position},
+    testing::SimApp,
+    wall::{BOTTOM_WALL, LEFT_WALL, RIGHT_WALL, TOP_WALL, constrain_objects},
+};
+use proptest::prelude::*;
+
+/// Zen, so overflowing doesn't end the run and stop the physics.
+fn sim() -> SimApp {
+    SimApp::new(HeadlessConfig {
+        mode: GameMode::Zen,
+        seed: Some(1),
+        ..default()
+    })
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn tier() -> impl Strategy<Value = Tier> {
+    (0..TIER_DIAMETERS.len() as u8).prop_map(Tier)
+}
+
+fn inside() -> impl Strategy<Value = Vec2> {
+    (LEFT_WALL..RIGHT_WALL, TOP_WALL..BOTTOM_WALL).prop_map(|(x, y)| vec2(x, y))
+}
+
+fn velocity() -> impl Strategy<Value = Vec2> {
+    (-200f32..200., -200f32..200.).prop_map(|(x, y)| vec2(x, y))
+}
+
+/// Some fruit anywhere in the arena, overlapping or not. Some share the centre of the one
+/// before, where there's no direction to push them apart in.
+fn fruits() -> impl Strategy<Value = Vec<(Tier, Vec2, Vec2)>> {
+    prop::collection::vec(
+        (tier(), inside(), velocity(), prop::bool::weighted(0.2)),
+        1..12,
+    )
+    .prop_map(|fruits| {
+        let mut last = None;
+        fruits
+            .into_iter()
+            .map(|(tier, pos, vel, stacked)| {
+                let pos = last.filter(|_| stacked).unwrap_or(pos);
+                last = Some(pos);
+                (tier, pos, vel)
+            })
+            .collect()
+    })
+}
+
+fn check(fruit: &LiveFruit) -> Result<(), TestCaseError> {
+    prop_assert!(fruit.pos.is_finite(), "{fruit:?} went somewhere strange");
+    prop_assert!(fruit.velocity.is_finite(), "{fruit:?} sped off");
+    let r = fruit.diameter / 2.;
+    prop_assert!(
+        (LEFT_WALL + r..=RIGHT_WALL - r).contains(&fruit.pos.x)
+            && (TOP_WALL + r..=BOTTOM_WALL - r).contains(&fruit.pos.y),
+        "{fruit:?} got out"
+    );
+    Ok(())
+}
+
+/// Every fruit has the same mass, so this is in units of it.
+fn kinetic_energy(fruits: Query<&Velocity, With<Fruit>>) -> f32 {
+    fruits.iter().map(|v| v.0.length_squared() / 2.).sum()
+}
+
+/// Energy going into and coming out of the last collision step.
+#[derive(Resource, Default)]
+struct CollisionEnergy {
+    before: f32,
+    after: f32,
+}
+
+fn energy_before(In(energy): In<f32>, mut seen: ResMut<CollisionEnergy>) {
+    seen.before = energy;
+}
+
+fn energy_after(In(energy): In<f32>, mut seen: ResMut<CollisionEnergy>) {
+    seen.after = energy;
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn fruit_stay_finite_and_inside(fruits in fruits()) {
+        let mut sim = sim();
+        for (tier, pos, vel) in fruits {
+            sim.spawn_fruit(tier, pos, vel);
+        }
+        for _ in 0..300 {
+            sim.step(1);
+            for fruit in sim.fruits() {
+                check(&fruit)?;
+            }
+        }
+    }
+
+    #[test]
+    fn collisions_never_add_energy(fruits in fruits()) {
+        let mut sim = sim();
+        sim.app_mut().init_resource::<CollisionEnergy>().add_systems(
+            FixedUpdate,
+            (
+                kinetic_energy
+                    .pipe(energy_before)
+                    .after(integrate_position)
+                    .before(apply_collisions),
+                kinetic_energy
+                    .pipe(energy_after)
+                    .after(apply_collisions)
+                    .before(constrain_objects),
+            ),
+        );
+        for (tier, pos, vel) in fruits {
+            sim.spawn_fruit(tier, pos, vel);
+        }
+        for tick in 0..300 {
+            sim.step(1);
+            let energy = sim.world().resource::<CollisionEnergy>();
+            prop_assert!(
+                energy.after <= energy.before * (1. + 1e-4) + 1e-3,
+                "energy went from {} to {} on tick {tick}",
+                energy.before,
+                energy.after
+            );
+        }
+    }
+
+    #[test]
+    fn random_drops_settle(drops in prop::collection::vec(LEFT_WALL..RIGHT_WALL, 1..16)) {
+        let mut sim = sim();
+        for x in drops {
+            prop_assert!(sim.drop_at(x, 600).is_some(), "the dropper stuck");
+        }
+        prop_assert!(sim.settle(3000), "still moving after 3000 ticks");
+        for fruit in sim.fruits() {
+            check(&fruit)?;
+        }
+    }
+}
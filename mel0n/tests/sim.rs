@@ -0,0 +1,234 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+use mel0n::{
+    dropper::{Dropper, SPAWN_ZONE_DEPTH},
+    fruit::Tier,
+    headless::HeadlessConfig,
+    input::TickInput,
+    merge::ArenaClearEvent,
+    mode::{GameMode, GameState},
+    overflow::DANGER_LINE,
+    rules::{GameRules, PhysicsPreset},
+    score::merge_points,
+    testing::SimApp,
+    wall::{BOTTOM_WALL, LEFT_WALL, RIGHT_WALL},
+};
+
+/// Where a fruit of `tier` rests on the floor, centred on `x`.
+fn on_floor(tier: Tier, x: f32) -> Vec2 {
+    vec2(x, BOTTOM_WALL - tier.diameter() / 2.)
+}
+
+#[test]
+fn single_drop_settles() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        seed: Some(7),
+        ..default()
+    });
+    let dropped = sim.drop_at(120., 60).expect("the dropper is ready");
+    let tier = sim.fruit(dropped).unwrap().tier;
+
+    assert!(sim.settle(600), "never came to rest");
+
+    let fruit = sim.fruit(dropped).unwrap();
+    assert!(
+        fruit.pos.distance(on_floor(tier, 120.)) < 0.5,
+        "rested at {}",
+        fruit.pos
+    );
+    assert_eq!(sim.state(), GameState::Playing);
+    assert_eq!(sim.score(), 0);
+}
+
+#[test]
+fn nothing_moves_while_paused() {
+    let mut sim = SimApp::default();
+    let dropped = sim.drop_at(120., 60).expect("the dropper is ready");
+    sim.step(5);
+    let (ticks, falling) = (sim.ticks(), sim.fruit(dropped));
+
+    sim.set_paused(true);
+    sim.step(30);
+    assert_eq!((sim.ticks(), sim.fruit(dropped)), (ticks, falling));
+
+    sim.set_paused(false);
+    sim.step(1);
+    assert_ne!(sim.fruit(dropped), falling);
+}
+
+#[test]
+fn moon_drops_clear_the_danger_line() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        physics: PhysicsPreset::Moon,
+        seed: Some(7),
+        ..default()
+    });
+    let dropped = sim.drop_at(120., 60).expect("the dropper is ready");
+    sim.step(180);
+
+    let fruit = sim.fruit(dropped).unwrap();
+    assert!(fruit.pos.y - fruit.diameter / 2. >= DANGER_LINE);
+    assert_eq!(sim.state(), GameState::Playing);
+}
+
+#[test]
+fn only_zen_holds_fruit() {
+    for (mode, holds) in [(GameMode::Classic, false), (GameMode::Zen, true)] {
+        let mut sim = SimApp::new(HeadlessConfig {
+            mode,
+            seed: Some(7),
+            ..default()
+        });
+        let current = sim.world().resource::<Dropper>().current;
+        sim.input(TickInput {
+            hold: true,
+            ..default()
+        });
+        sim.step(1);
+        let held = sim.world().resource::<Dropper>().held;
+        assert_eq!(held, holds.then_some(current), "{}", mode.name());
+    }
+}
+
+/// Presses drop for one tick.
+fn mash_drop(sim: &mut SimApp) {
+    sim.input(TickInput {
+        drop: true,
+        ..default()
+    });
+    sim.step(1);
+}
+
+#[test]
+fn mashing_drop_waits_for_the_last_fruit_to_leave_the_spawn_zone() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        seed: Some(7),
+        ..default()
+    });
+    // Only the spawn zone holds the dropper back.
+    sim.app_mut()
+        .world_mut()
+        .resource_mut::<GameRules>()
+        .drop_cooldown = Duration::ZERO;
+    let dropped = sim.drop_at(120., 60).expect("the dropper is ready");
+
+    let in_spawn_zone = |sim: &SimApp| {
+        let fruit = sim.fruit(dropped).unwrap();
+        fruit.pos.y - fruit.diameter / 2. < SPAWN_ZONE_DEPTH
+    };
+    let mut ticks = 0;
+    while in_spawn_zone(&sim) {
+        mash_drop(&mut sim);
+        assert_eq!(sim.fruits().len(), 1, "dropped again on tick {ticks}");
+        ticks += 1;
+    }
+    assert!(ticks > 1, "left the spawn zone straight away");
+
+    mash_drop(&mut sim);
+    assert_eq!(sim.fruits().len(), 2);
+}
+
+#[test]
+fn dropper_is_ready_after_the_cooldown() {
+    let mut sim = SimApp::new(HeadlessConfig {
+        seed: Some(7),
+        ..default()
+    });
+    let cooldown = {
+        let mut rules = sim.app_mut().world_mut().resource_mut::<GameRules>();
+        // Only the cooldown holds the dropper back.
+        rules.single_active_drop = false;
+        rules.drop_cooldown
+    };
+    mash_drop(&mut sim);
+    assert_eq!(sim.fruits().len(), 1);
+
+    let tick = HeadlessConfig::default().tick;
+    let ticks = u32::try_from(cooldown.as_nanos().div_ceil(tick.as_nanos())).unwrap();
+    // The drop's own tick counts towards it.
+    sim.step(ticks - 2);
+    assert!(!sim.world().resource::<Dropper>().can_drop());
+    sim.step(1);
+    assert!(sim.world().resource::<Dropper>().can_drop());
+}
+
+#[test]
+fn two_fruit_merge() {
+    let mut sim = SimApp::default();
+    let tier = Tier(2);
+    let below = sim.spawn_fruit(tier, on_floor(tier, 100.), Vec2::ZERO);
+    let above = sim.spawn_fruit(tier, vec2(100., 40.), Vec2::ZERO);
+
+    assert!(
+        sim.step_until(300, |sim| sim.fruits().len() == 1),
+        "never met"
+    );
+
+    let merged = sim.fruits()[0];
+    assert!([below, above].contains(&merged.entity));
+    assert_eq!(merged.tier, Tier(3));
+    assert_eq!(sim.score(), merge_points(Tier(3)));
+}
+
+#[test]
+fn top_tier_pair_clears() {
+    let mut sim = SimApp::default();
+    sim.watch::<ArenaClearEvent>();
+    let d = Tier::MAX.diameter();
+    sim.spawn_fruit(
+        Tier::MAX,
+        on_floor(Tier::MAX, LEFT_WALL + d / 2.),
+        Vec2::ZERO,
+    );
+    sim.spawn_fruit(
+        Tier::MAX,
+        on_floor(Tier::MAX, LEFT_WALL + d * 1.5),
+        Vec2::ZERO,
+    );
+
+    sim.step(1);
+
+    assert!(sim.fruits().is_empty());
+    assert_eq!(sim.events::<ArenaClearEvent>().len(), 1);
+    assert_eq!(sim.score(), sim.events::<ArenaClearEvent>()[0].bonus);
+}
+
+#[test]
+fn stack_stays_stable() {
+    let mut sim = SimApp::default();
+    // A row along the floor from wall to wall, no two neighbours alike, with a second row in
+    // its dips.
+    let mut x = LEFT_WALL;
+    let mut row = Vec::new();
+    for tier in [Tier(4), Tier(6), Tier(5), Tier(9)] {
+        let pos = on_floor(tier, x + tier.diameter() / 2.);
+        sim.spawn_fruit(tier, pos, Vec2::ZERO);
+        row.push(pos);
+        x += tier.diameter();
+    }
+    assert!((x - RIGHT_WALL).abs() < 1e-3);
+    for (pair, tier) in row.windows(2).zip([Tier(0), Tier(1), Tier(2)]) {
+        let above = vec2(
+            f32::midpoint(pair[0].x, pair[1].x),
+            pair[0].y.min(pair[1].y) - 20.,
+        );
+        sim.spawn_fruit(tier, above, Vec2::ZERO);
+    }
+    assert!(sim.settle(600), "never came to rest");
+    let rested = sim.fruits();
+
+    sim.step(600);
+
+    let after = sim.fruits();
+    assert_eq!(after.len(), rested.len(), "fruit merged or left");
+    for (before, after) in rested.iter().zip(&after) {
+        assert!(
+            before.pos.distance(after.pos) < 0.5,
+            "{:?} crept from {} to {}",
+            before.tier,
+            before.pos,
+            after.pos
+        );
+    }
+}